@@ -0,0 +1,221 @@
+use crate::cache::PatternTable;
+use crate::error::{Error, Result};
+use crate::guess::{FeedBack, Guess};
+use std::cmp::Reverse;
+
+/// Number of best words, by worst case, looked one guess further into.
+const LOOKAHEAD: usize = 10;
+
+/// Absurdle host: instead of picking an answer, it answers every guess with
+/// the feedback that keeps the most candidates.
+#[derive(Clone, Debug)]
+pub struct Host {
+    candidates: Guess,
+    num_guess: u32,
+    solved: bool,
+}
+
+/// The bucket the host keeps when `word` is played, and its feedback.
+///
+/// Ties go to the feedback with the fewest greens, then the fewest yellows,
+/// so the host gives away as little as possible.
+fn host_choice(
+    candidates: &Guess,
+    word: &str,
+    dict: &[String],
+    cache: &PatternTable,
+) -> Option<(Vec<FeedBack>, Guess)> {
+    let count = |feedback: &[FeedBack], colour| feedback.iter().filter(|&&fb| fb == colour).count();
+    candidates
+        .partition(word, dict, cache)
+        .into_iter()
+        .max_by_key(|(feedback, bucket)| {
+            (
+                bucket.num_solutions(),
+                Reverse(count(feedback, FeedBack::Green)),
+                Reverse(count(feedback, FeedBack::Yellow)),
+                Reverse(feedback.clone()),
+            )
+        })
+}
+
+impl Host {
+    pub fn new(dict: &[String]) -> Self {
+        Self {
+            candidates: Guess::new(dict),
+            num_guess: 0,
+            solved: false,
+        }
+    }
+    /// Words the host can still claim to have picked.
+    pub fn candidates(&self) -> &Guess {
+        &self.candidates
+    }
+    pub fn num_guess(&self) -> u32 {
+        self.num_guess
+    }
+    /// Whether the last guess was the only word left.
+    pub fn is_solved(&self) -> bool {
+        self.solved
+    }
+    /// Answer `word` with the feedback keeping the largest bucket of candidates.
+    pub fn respond(
+        &mut self,
+        word: &str,
+        dict: &[String],
+        cache: &PatternTable,
+    ) -> Result<Vec<FeedBack>> {
+        if self.solved {
+            return Err(Error::InvalidInput("the game is over".to_string()));
+        }
+        if !dict.iter().any(|w| w == word) {
+            return Err(Error::InvalidInput(format!(
+                "{:?} is not in the dictionary",
+                word
+            )));
+        }
+        let (feedback, bucket) = host_choice(&self.candidates, word, dict, cache)
+            .ok_or_else(|| Error::Search("no candidate word is left".to_string()))?;
+        self.candidates = bucket;
+        self.num_guess += 1;
+        self.solved = feedback.iter().all(|&fb| fb == FeedBack::Green);
+        Ok(feedback)
+    }
+}
+
+/// Let the solver play against `host` until it gives in, calling `on_turn`
+/// with each word played and the feedback it got.
+pub fn self_play(
+    host: &mut Host,
+    dict: &[String],
+    cache: &PatternTable,
+    mut on_turn: impl FnMut(&Host, &str, &[FeedBack]),
+) -> Result<()> {
+    while !host.is_solved() {
+        let word = minimax_guess(host.candidates(), dict, cache)?;
+        let feedback = host.respond(&word, dict, cache)?;
+        on_turn(host, &word, &feedback);
+    }
+    Ok(())
+}
+
+/// Size of the largest bucket `word` splits `candidates` into, the most the host can keep.
+fn worst_case(candidates: &Guess, word: &str, dict: &[String], cache: &PatternTable) -> usize {
+    candidates
+        .feedback_counts(word, dict, cache)
+        .into_values()
+        .max()
+        .unwrap_or(0)
+}
+
+/// Best word against the [`Host`], minimizing the candidates it can keep.
+///
+/// Words are ranked by their worst case, then the most promising few are
+/// played out one guess further since the host's answer is deterministic.
+pub fn minimax_guess(candidates: &Guess, dict: &[String], cache: &PatternTable) -> Result<String> {
+    let solutions = candidates.solutions(dict);
+    match solutions.len() {
+        0 => return Err(Error::Search("no candidate word is left".to_string())),
+        1 | 2 => return Ok(solutions[0].clone()),
+        _ => {}
+    }
+    let mut ranked: Vec<(usize, bool, &String)> = dict
+        .iter()
+        .map(|word| {
+            (
+                worst_case(candidates, word, dict, cache),
+                !candidates.contains(word, dict),
+                word,
+            )
+        })
+        .collect();
+    ranked.sort();
+    ranked
+        .into_iter()
+        .take(LOOKAHEAD)
+        .map(|(worst, not_candidate, word)| {
+            let next = match host_choice(candidates, word, dict, cache) {
+                Some((_, bucket)) if worst > 1 => dict
+                    .iter()
+                    .map(|w| worst_case(&bucket, w, dict, cache))
+                    .min()
+                    .unwrap_or(0),
+                _ => 0,
+            };
+            ((next, worst, not_candidate), word)
+        })
+        .min()
+        .map(|(_, word)| word.clone())
+        .ok_or_else(|| Error::Search("the dictionary is empty".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dict() -> Vec<String> {
+        ["crane", "crate", "trace", "slate", "plate", "pious"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn host_keeps_the_largest_bucket() {
+        let dict = dict();
+        let cache = PatternTable::new();
+        let mut host = Host::new(&dict);
+        let feedback = host.respond("pious", &dict, &cache).unwrap();
+        assert_eq!(feedback, FeedBack::parse("bbbbb").unwrap());
+        assert_eq!(
+            host.candidates().solutions(&dict),
+            ["crane", "crate", "trace"]
+        );
+        assert!(!host.is_solved());
+        assert!(host.respond("pizza", &dict, &cache).is_err());
+        assert_eq!(host.num_guess(), 1);
+    }
+
+    #[test]
+    fn ties_go_to_the_feedback_giving_away_the_least() {
+        let dict: Vec<String> = ["crane", "slate"].map(String::from).to_vec();
+        let mut host = Host::new(&dict);
+        // Both words are a bucket of one, so the host does not admit a win
+        let feedback = host.respond("crane", &dict, &PatternTable::new()).unwrap();
+        assert_eq!(feedback, FeedBack::compute("crane", "slate"));
+        assert_eq!(host.candidates().solutions(&dict), ["slate"]);
+    }
+
+    #[test]
+    fn minimax_leaves_the_host_the_fewest_words() {
+        let dict = dict();
+        let cache = PatternTable::new();
+        let candidates = Guess::new(&dict);
+        let word = minimax_guess(&candidates, &dict, &cache).unwrap();
+        let best = dict
+            .iter()
+            .map(|w| worst_case(&candidates, w, &dict, &cache))
+            .min()
+            .unwrap();
+        assert_eq!(worst_case(&candidates, &word, &dict, &cache), best);
+        let solved = candidates
+            .refine("pious", &FeedBack::parse("ggggg").unwrap(), &dict)
+            .unwrap();
+        assert_eq!(minimax_guess(&solved, &dict, &cache).unwrap(), "pious");
+    }
+
+    #[test]
+    fn self_play_goes_on_until_the_host_gives_in() {
+        let dict = dict();
+        let cache = PatternTable::new();
+        let mut host = Host::new(&dict);
+        let mut rows = Vec::new();
+        self_play(&mut host, &dict, &cache, |_, _, feedback| {
+            rows.push(feedback.to_vec())
+        })
+        .unwrap();
+        assert!(host.is_solved());
+        assert_eq!(rows.len() as u32, host.num_guess());
+        assert!(rows.last().unwrap().iter().all(|&fb| fb == FeedBack::Green));
+        assert!(host.respond("crane", &dict, &cache).is_err());
+    }
+}
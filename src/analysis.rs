@@ -0,0 +1,282 @@
+use crate::error::{Error, Result};
+use crate::guess::{FeedBack, Guess};
+use crate::mcts::{SearchOptions, Suggestion};
+use crate::solver::Solver;
+use serde::Serialize;
+
+/// Candidates expected to be left for each further guess, when estimating the
+/// guesses a candidate set takes to solve. Chosen so the whole answer list of
+/// Wordle takes about 3.6 guesses.
+const SPLIT: f32 = 30.0;
+
+/// Rough number of guesses needed to solve `candidates` candidates, the
+/// last one included.
+///
+/// Two candidates take 1.5 guesses on average, and every guess after that
+/// divides the candidates by [`SPLIT`].
+pub fn guesses_to_solve(candidates: usize) -> f32 {
+    match candidates {
+        0 | 1 => 1.0,
+        n => 1.5 + (n as f32 / 2.0).ln() / SPLIT.ln(),
+    }
+}
+
+/// Expected number of guesses to finish the game by playing `word` from `guess`, `word` included.
+///
+/// Estimated from how the candidates split, for the words the search did not
+/// simulate.
+pub fn expected_guesses(solver: &Solver, guess: &Guess, word: &str) -> f32 {
+    let (dict, weights) = (solver.dict(), solver.weights());
+    let total = guess.weight(weights);
+    if total <= 0.0 {
+        return 0.0;
+    }
+    guess
+        .feedback_weights(word, dict, weights, solver.cache())
+        .iter()
+        .map(|(feedback, &(count, weight))| {
+            let solved = feedback.iter().all(|&fb| fb == FeedBack::Green);
+            let guesses = if solved {
+                1.0
+            } else {
+                1.0 + guesses_to_solve(count)
+            };
+            weight / total * guesses
+        })
+        .sum()
+}
+
+/// Expected number of guesses to finish the game, from the statistics the
+/// search gathered for a word played as guess `num_guess + 1`.
+///
+/// A simulation is won once a single candidate is left and scores the guesses
+/// left then, so the answer is known after `max_guess - mean_score /
+/// win_probability` guesses on average. It still takes one more to play it,
+/// unless it is the word played, the answer with probability `answer_share`.
+/// A loss counts as every guess left and one more.
+pub fn searched_guesses(
+    suggestion: &Suggestion,
+    max_guess: u8,
+    num_guess: u8,
+    answer_share: f32,
+) -> f32 {
+    let left = max_guess as f32 - num_guess as f32;
+    let p = suggestion.win_probability;
+    if p <= 0.0 {
+        return left + 1.0;
+    }
+    let won = left - suggestion.mean_score / p + 1.0 - answer_share;
+    p * won + (1.0 - p) * (left + 1.0)
+}
+
+/// Candidates left once `word` got `feedback`: the words of `guess` that would
+/// have given the very same feedback.
+///
+/// Feedback the pattern table never gives, such as Wordle's black for a
+/// repeated letter, falls back to [`Solver::refine`].
+pub fn candidates_after(
+    solver: &Solver,
+    guess: &Guess,
+    word: &str,
+    feedback: &[FeedBack],
+) -> Result<Guess> {
+    let refined = solver.refine(guess, word, feedback)?;
+    Ok(guess
+        .partition(word, solver.dict(), solver.cache())
+        .remove(feedback)
+        .unwrap_or(refined))
+}
+
+/// One turn of a game graded against the bot.
+#[derive(Clone, Debug, Serialize)]
+pub struct TurnAnalysis {
+    pub word: String,
+    /// Feedback received, as a pattern such as `bgybb`
+    pub feedback: String,
+    pub candidates_before: usize,
+    pub candidates_after: usize,
+    /// The word the bot would have played
+    pub best: String,
+    /// Expected guesses to finish the game with the word played, itself included
+    pub expected_guesses: f32,
+    /// Expected guesses to finish the game with the bot's word
+    pub best_expected_guesses: f32,
+    /// Guesses lost on average by not playing the bot's word, negative when the word played was better
+    pub difference: f32,
+    /// How close the word played is to the bot's word, from 0 to 99
+    pub skill: u8,
+    /// Share of the possible feedbacks that would have left more candidates, from 0 to 99
+    pub luck: u8,
+}
+
+/// A whole game graded turn by turn.
+#[derive(Clone, Debug, Serialize)]
+pub struct Analysis {
+    pub turns: Vec<TurnAnalysis>,
+    pub solved: bool,
+    /// Mean skill over the turns
+    pub skill: u8,
+    /// Mean luck over the turns
+    pub luck: u8,
+}
+
+/// Score from 0 to 99 of a fraction between 0 and 1.
+fn grade(fraction: f32) -> u8 {
+    (fraction.clamp(0.0, 1.0) * 99.0).round() as u8
+}
+
+/// Grade the words played and the feedback they got, in order, against the bot.
+///
+/// Every turn searches for the bot's word from the candidates left then, so
+/// the analysis takes about as long as asking the bot for every guess. The
+/// expected guesses come from that search, for every word it simulated.
+pub fn analyze(solver: &Solver, plays: &[(String, Vec<FeedBack>)]) -> Result<Analysis> {
+    let dict = solver.dict();
+    let options = SearchOptions {
+        top: usize::MAX,
+        ..solver.options().clone()
+    };
+    let mut guess = solver.new_game();
+    let mut turns = Vec::new();
+    for (i, (word, feedback)) in plays.iter().enumerate() {
+        let after = candidates_after(solver, &guess, word, feedback)?;
+        if after.num_solutions() == 0 {
+            return Err(Error::InvalidInput(format!(
+                "no word of the dictionary matches turn {} ({})",
+                i + 1,
+                word
+            )));
+        }
+        let result = solver.search_with(&guess, i as u8, &options)?;
+        let best = result.best().word.clone();
+        let total = guess.weight(solver.weights());
+        let searched = |word: &str| {
+            let suggestion = result.suggestions.iter().find(|s| s.word == word)?;
+            let share = dict
+                .iter()
+                .position(|w| w == word)
+                .map_or(0.0, |i| solver.weights()[i] / total);
+            Some(searched_guesses(
+                suggestion,
+                options.max_guess,
+                i as u8,
+                share,
+            ))
+        };
+        // Words the search never played, as words that cannot be the answer,
+        // get both their estimates from the split of the candidates instead
+        let (expected, best_expected) = match (searched(word), searched(&best)) {
+            (Some(expected), Some(best_expected)) => (expected, best_expected),
+            _ => (
+                expected_guesses(solver, &guess, word),
+                expected_guesses(solver, &guess, &best),
+            ),
+        };
+        // Outcomes leaving more candidates than the actual one were worse luck, ties count half
+        let left = after.num_solutions();
+        let luck = guess
+            .feedback_weights(word, dict, solver.weights(), solver.cache())
+            .values()
+            .map(|&(count, weight)| {
+                let share = weight / total;
+                match count.cmp(&left) {
+                    std::cmp::Ordering::Greater => share,
+                    std::cmp::Ordering::Equal => share / 2.0,
+                    std::cmp::Ordering::Less => 0.0,
+                }
+            })
+            .sum::<f32>();
+        turns.push(TurnAnalysis {
+            word: word.clone(),
+            feedback: feedback.iter().map(|fb| fb.to_char()).collect(),
+            candidates_before: guess.num_solutions(),
+            candidates_after: left,
+            best,
+            expected_guesses: expected,
+            best_expected_guesses: best_expected,
+            difference: expected - best_expected,
+            skill: grade(best_expected / expected),
+            luck: grade(luck),
+        });
+        guess = after;
+    }
+    let mean = |grades: Vec<u8>| {
+        if grades.is_empty() {
+            return 0;
+        }
+        (grades.iter().map(|&g| g as f32).sum::<f32>() / grades.len() as f32).round() as u8
+    };
+    Ok(Analysis {
+        solved: plays
+            .last()
+            .is_some_and(|(_, feedback)| feedback.iter().all(|&fb| fb == FeedBack::Green)),
+        skill: mean(turns.iter().map(|turn| turn.skill).collect()),
+        luck: mean(turns.iter().map(|turn| turn.luck).collect()),
+        turns,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PatternTable;
+    use crate::progress::Verbosity;
+    use crate::store::tests::temp_store;
+
+    fn solver(words: &[&str]) -> Solver {
+        let dict: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        let weights = vec![1.0; dict.len()];
+        let options = SearchOptions {
+            iterations: 20,
+            threads: 2,
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        Solver::new(dict, weights, PatternTable::new(), temp_store(), options)
+    }
+
+    #[test]
+    fn after_set_is_the_bucket_of_the_feedback() {
+        // Refine keeps "cabin" for an all black "about", though its "a" would be yellow
+        let solver = solver(&["about", "crisp", "cabin"]);
+        let feedback = FeedBack::parse("bbbbb").unwrap();
+        let after = candidates_after(&solver, &solver.new_game(), "about", &feedback).unwrap();
+        assert_eq!(after.solutions(solver.dict()), ["crisp"]);
+    }
+
+    #[test]
+    fn searched_guesses_count_wins_and_losses() {
+        let mut suggestion = Suggestion {
+            word: "crane".to_string(),
+            mean_score: 3.0,
+            visits: 10,
+            win_probability: 1.0,
+            expected_remaining: 0.0,
+            is_candidate: true,
+        };
+        // Known after 3 guesses, played with a fourth
+        assert_eq!(searched_guesses(&suggestion, 6, 0, 0.0), 4.0);
+        // Known after the one guess left, which is the answer half the time
+        assert_eq!(searched_guesses(&suggestion, 6, 2, 0.5), 1.5);
+        // Half the games won after 2 guesses, half lost after all 6 and one more
+        suggestion.mean_score = 2.0;
+        suggestion.win_probability = 0.5;
+        assert_eq!(searched_guesses(&suggestion, 6, 0, 1.0), 4.5);
+        suggestion.win_probability = 0.0;
+        assert_eq!(searched_guesses(&suggestion, 6, 0, 0.0), 7.0);
+    }
+
+    #[test]
+    fn luck_and_candidates_agree_on_the_feedback() {
+        let solver = solver(&["crane", "crate", "trace", "slate", "plate", "pious"]);
+        let feedback = FeedBack::compute("crane", "pious");
+        let analysis = analyze(&solver, &[("crane".to_string(), feedback)]).unwrap();
+        let turn = &analysis.turns[0];
+        assert_eq!(turn.candidates_before, 6);
+        // "pious" is alone in its bucket, the best luck the other buckets allow
+        assert_eq!(turn.candidates_after, 1);
+        assert!(turn.luck > 50, "luck {}", turn.luck);
+        assert!(turn.expected_guesses >= 1.0);
+        assert!(!analysis.solved);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::{Error, Result};
+use crate::guess::{FeedBack, Guess};
+use crate::mcts::SearchResult;
+use crate::solver::Solver;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Version of the book file format, bumped whenever it changes.
+pub const VERSION: u32 = 1;
+
+/// Best words precomputed for the first guesses, so games do not start with the most expensive search.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Book {
+    pub version: u32,
+    /// Fingerprint of the dictionary, weights and rules the book was computed for
+    pub fingerprint: String,
+    /// Number of guesses covered, 1 for the first guess only
+    pub depth: u8,
+    /// Search result for each history of `word:pattern` entries, `""` being the start of the game
+    pub entries: BTreeMap<String, SearchResult>,
+}
+
+/// FNV-1a hash of the dictionary, the weights and the max number of guesses,
+/// stable across builds unlike the standard library hasher.
+pub fn fingerprint(solver: &Solver) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+    for word in solver.dict() {
+        feed(word.as_bytes());
+        feed(b"\n");
+    }
+    for weight in solver.weights() {
+        feed(&weight.to_bits().to_le_bytes());
+    }
+    feed(&[solver.options().max_guess]);
+    format!("{:016x}", hash)
+}
+
+fn entry(word: &str, feedback: &[FeedBack]) -> String {
+    format!(
+        "{}:{}",
+        word,
+        feedback.iter().map(|fb| fb.to_char()).collect::<String>()
+    )
+}
+
+impl Book {
+    /// Search the best first guess and, down to `depth` guesses, the best
+    /// word after every feedback the previous best word can get.
+    ///
+    /// `on_entry` is called with the number of entries done and the history
+    /// of each one as it is searched.
+    pub fn generate(
+        solver: &Solver,
+        depth: u8,
+        mut on_entry: impl FnMut(usize, &str),
+    ) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        let mut frontier = vec![(Vec::<String>::new(), solver.new_game())];
+        for num_guess in 0..depth {
+            let mut next = Vec::new();
+            for (history, guess) in frontier {
+                let key = history.join(" ");
+                on_entry(entries.len(), &key);
+                let result = solver.search_live(&guess, num_guess, solver.options())?;
+                let best = result.best().word.clone();
+                entries.insert(key, result);
+                for (feedback, refined) in guess.partition(&best, solver.dict(), solver.cache()) {
+                    if feedback.iter().all(|&fb| fb == FeedBack::Green) {
+                        continue;
+                    }
+                    let mut history = history.clone();
+                    history.push(entry(&best, &feedback));
+                    next.push((history, refined));
+                }
+            }
+            frontier = next;
+        }
+        Ok(Self {
+            version: VERSION,
+            fingerprint: fingerprint(solver),
+            depth,
+            entries,
+        })
+    }
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| Error::Parse(format!("cannot serialize the book: {}", e)))?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+    /// Read the book at `path`, refusing it when it was computed for another
+    /// version, dictionary or rules than those of `solver`.
+    pub fn load(path: &Path, solver: &Solver) -> Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let book: Self = serde_json::from_str(&text)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+        if book.version != VERSION {
+            return Err(Error::InvalidInput(format!(
+                "{} is a version {} book, expected version {}",
+                path.display(),
+                book.version,
+                VERSION
+            )));
+        }
+        if book.fingerprint != fingerprint(solver) {
+            return Err(Error::InvalidInput(format!(
+                "{} was computed for another dictionary or max number of guesses",
+                path.display()
+            )));
+        }
+        Ok(book)
+    }
+    /// Key the entries by the candidates and number of guesses they start
+    /// from, so any history leading there finds them.
+    pub fn positions(&self, solver: &Solver) -> Result<Vec<((Guess, u8), SearchResult)>> {
+        self.entries
+            .iter()
+            .map(|(history, result)| {
+                let history: Vec<String> = history.split_whitespace().map(String::from).collect();
+                let guess = solver.replay(&history)?;
+                Ok(((guess, history.len() as u8), result.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PatternTable;
+    use crate::mcts::SearchOptions;
+    use crate::progress::Verbosity;
+    use crate::store::tests::temp_store;
+
+    fn solver(max_guess: u8) -> Solver {
+        let dict: Vec<String> = ["crane", "crate", "trace", "slate", "plate", "pious"]
+            .map(String::from)
+            .to_vec();
+        let weights = vec![1.0; dict.len()];
+        let options = SearchOptions {
+            max_guess,
+            iterations: 10,
+            threads: 1,
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        Solver::new(dict, weights, PatternTable::new(), temp_store(), options)
+    }
+
+    #[test]
+    fn book_covers_every_feedback_of_the_best_word() {
+        let solver = solver(6);
+        let mut histories = Vec::new();
+        let book =
+            Book::generate(&solver, 2, |_, history| histories.push(history.to_string())).unwrap();
+        // Every entry is reported as it is searched, in no particular order
+        histories.sort();
+        assert_eq!(histories, book.entries.keys().cloned().collect::<Vec<_>>());
+        let best = book.entries[""].best().word.clone();
+        let buckets = solver
+            .new_game()
+            .partition(&best, solver.dict(), solver.cache())
+            .into_keys()
+            .filter(|feedback| feedback.iter().any(|&fb| fb != FeedBack::Green))
+            .count();
+        assert_eq!(book.entries.len(), 1 + buckets);
+        assert!(book
+            .entries
+            .keys()
+            .skip(1)
+            .all(|history| history.starts_with(&format!("{}:", best))));
+    }
+
+    #[test]
+    fn book_is_only_used_for_the_dictionary_it_was_computed_for() {
+        let mut solver = solver(6);
+        let book = Book::generate(&solver, 1, |_, _| {}).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("wordle-bot-{}-book.json", std::process::id()));
+        book.save(&path).unwrap();
+        let loaded = Book::load(&path, &solver);
+        let other = Book::load(&path, &self::solver(5));
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert!(other.is_err());
+        assert_ne!(fingerprint(&solver), fingerprint(&self::solver(5)));
+
+        solver.use_book(&loaded).unwrap();
+        let result = solver.search(&solver.new_game(), 0).unwrap();
+        assert!(result.from_book);
+        assert_eq!(result.best().word, book.entries[""].best().word);
+        // Positions past the book are searched
+        let after = solver.replay(&["pious:bbbbb".to_string()]).unwrap();
+        assert!(!solver.search(&after, 1).unwrap().from_book);
+    }
+}
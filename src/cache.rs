@@ -0,0 +1,382 @@
+use crate::error::{self, Error};
+use crate::guess::FeedBack;
+use crate::progress::{Progress, Verbosity};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Feedback of every (word, solution) pair of a dictionary.
+pub type PatternTable = HashMap<(String, String), Vec<FeedBack>>;
+
+/// Error reading the cache file.
+#[derive(Debug)]
+pub enum CacheError {
+    Io(io::Error),
+    /// A line of the cache file could not be parsed
+    Malformed {
+        line: usize,
+        reason: String,
+    },
+    /// Word pairs of the dictionary with no entry in the cache
+    Incomplete {
+        missing: usize,
+    },
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CacheError::Io(e) => write!(f, "{}", e),
+            CacheError::Malformed { line, reason } => write!(f, "line {}: {}", line, reason),
+            CacheError::Incomplete { missing } => {
+                write!(f, "{} word pairs of the dictionary are missing", missing)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<io::Error> for CacheError {
+    fn from(e: io::Error) -> Self {
+        CacheError::Io(e)
+    }
+}
+
+/// Number of worker threads to use for `threads`, 0 meaning one per core.
+pub fn worker_count(threads: usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Compute the row of each of `words`, its feedback against every one of
+/// `solutions`, split across `threads` workers.
+///
+/// `on_row` is called from the workers with the number of rows done so far.
+/// Fails when a worker panics.
+pub fn generate_rows(
+    words: &[String],
+    solutions: &[String],
+    threads: usize,
+    on_row: impl Fn(usize) + Sync,
+) -> error::Result<PatternTable> {
+    let done = AtomicUsize::new(0);
+    let chunk_size = words.len().div_ceil(worker_count(threads)).max(1);
+    thread::scope(|scope| {
+        let workers: Vec<_> = words
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let (done, on_row) = (&done, &on_row);
+                scope.spawn(move || {
+                    let mut rows = PatternTable::new();
+                    for word in chunk {
+                        for solution in solutions {
+                            rows.insert(
+                                (word.clone(), solution.clone()),
+                                FeedBack::compute(word, solution),
+                            );
+                        }
+                        on_row(done.fetch_add(1, Ordering::Relaxed) + 1);
+                    }
+                    rows
+                })
+            })
+            .collect();
+        // Every worker is joined, as the scope panics on one left unjoined that panicked
+        let rows: Vec<_> = workers.into_iter().map(|worker| worker.join()).collect();
+        let mut table = PatternTable::new();
+        for rows in rows {
+            table.extend(
+                rows.map_err(|_| Error::Search("a pattern table worker panicked".to_string()))?,
+            );
+        }
+        Ok(table)
+    })
+}
+
+/// Compute the feedback of every pair of words of `dict`, see [`generate_rows`].
+pub fn generate_cache(
+    dict: &[String],
+    threads: usize,
+    on_row: impl Fn(usize) + Sync,
+) -> error::Result<PatternTable> {
+    generate_rows(dict, dict, threads, on_row)
+}
+
+/// Words of `dict` whose row in `cache` misses some word of `dict`.
+pub fn missing_rows(cache: &PatternTable, dict: &[String]) -> Vec<String> {
+    let words: HashSet<&str> = dict.iter().map(String::as_str).collect();
+    let mut found: HashMap<&str, usize> = HashMap::new();
+    for (word, solution) in cache.keys() {
+        if words.contains(word.as_str()) && words.contains(solution.as_str()) {
+            *found.entry(word).or_insert(0) += 1;
+        }
+    }
+    dict.iter()
+        .filter(|word| found.get(word.as_str()).copied().unwrap_or(0) < dict.len())
+        .cloned()
+        .collect()
+}
+
+/// Add the rows `cache` misses for `dict`, so a table built for another
+/// dictionary only costs the new words. Returns the number of rows added.
+pub fn complete_cache(
+    cache: &mut PatternTable,
+    dict: &[String],
+    threads: usize,
+    on_row: impl Fn(usize, usize) + Sync,
+) -> error::Result<usize> {
+    let missing = missing_rows(cache, dict);
+    if missing.is_empty() {
+        return Ok(0);
+    }
+    let total = missing.len();
+    cache.extend(generate_rows(&missing, dict, threads, |done| {
+        on_row(done, total)
+    })?);
+    Ok(total)
+}
+
+/// The pattern table of `dict`, read from `path` and completed on `threads`
+/// workers, then written back when rows were added.
+///
+/// A corrupted file is rebuilt and a file that cannot be written is only
+/// reported, as the table in memory is all the search needs.
+pub fn load_table(
+    path: &Path,
+    dict: &[String],
+    threads: usize,
+    verbosity: Verbosity,
+) -> error::Result<PatternTable> {
+    let mut cache = match import_cache(path) {
+        Ok(cache) => cache,
+        Err(e) => {
+            if let (CacheError::Malformed { .. }, true) = (&e, verbosity > Verbosity::Quiet) {
+                eprintln!("{} is corrupted ({}), rebuilding", path.display(), e);
+            }
+            PatternTable::new()
+        }
+    };
+    if verbosity == Verbosity::Verbose {
+        eprintln!("{}: {} word pairs", path.display(), cache.len());
+    }
+    // Rows of words already in the table, from this or another dictionary, are kept
+    let missing = missing_rows(&cache, dict).len();
+    let progress = Mutex::new(Progress::new(
+        verbosity,
+        "pattern table",
+        (missing, "rows"),
+        (missing, "rows"),
+    ));
+    let added = complete_cache(&mut cache, dict, threads, |_, _| {
+        // Poisoned only by a worker that panicked, which completing reports
+        if let Ok(mut progress) = progress.lock() {
+            progress.advance(1, 1, String::new)
+        }
+    })?;
+    if added > 0 {
+        progress
+            .into_inner()
+            .map_err(|_| Error::Search("a pattern table worker panicked".to_string()))?
+            .finish("");
+        if let Err(e) = export_cache(&cache, path) {
+            eprintln!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+    Ok(cache)
+}
+
+/// Write `cache` to `out`, one `word,solution,pattern` line per pair.
+pub fn export_cache(cache: &PatternTable, out: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(fs::File::create(out)?);
+    for (key, value) in cache {
+        let mut line = "".to_string() + &key.0 + "," + &key.1 + ",";
+        line.extend(value.iter().map(|fb| fb.to_char()));
+        line += "\n";
+        file.write_all(line.as_bytes())?;
+    }
+    file.flush()
+}
+
+fn parse_line(
+    line: &str,
+    line_number: usize,
+) -> Result<((String, String), Vec<FeedBack>), CacheError> {
+    let malformed = |reason: String| CacheError::Malformed {
+        line: line_number,
+        reason,
+    };
+    let mut iter = line.split(',');
+    let (word, solution, pattern) = match (iter.next(), iter.next(), iter.next(), iter.next()) {
+        (Some(word), Some(solution), Some(pattern), None) => (word, solution, pattern),
+        _ => return Err(malformed(format!("expected 3 fields in {:?}", line))),
+    };
+    let mut feedback = Vec::new();
+    for fb in pattern.chars() {
+        match FeedBack::from_char(fb) {
+            Some(fb) => feedback.push(fb),
+            None => return Err(malformed(format!("invalid feedback {:?}", fb))),
+        }
+    }
+    if feedback.len() != word.chars().count() {
+        return Err(malformed(format!(
+            "feedback {:?} does not match the length of {:?}",
+            pattern, word
+        )));
+    }
+    Ok(((word.to_string(), solution.to_string()), feedback))
+}
+
+/// Read a cache written by [`export_cache`].
+pub fn import_cache(inp: &Path) -> Result<PatternTable, CacheError> {
+    let file = BufReader::new(fs::File::open(inp)?);
+    let mut cache = HashMap::new();
+    for (i, line) in file.lines().enumerate() {
+        let (key, feedback) = parse_line(&line?, i + 1)?;
+        cache.insert(key, feedback);
+    }
+    Ok(cache)
+}
+
+/// Check every line of the cache against `dict` without stopping at the first problem.
+pub fn verify_cache(inp: &Path, dict: &[String]) -> Result<Vec<CacheError>, io::Error> {
+    let file = BufReader::new(fs::File::open(inp)?);
+    let mut problems = Vec::new();
+    let mut cache = HashSet::new();
+    for (i, line) in file.lines().enumerate() {
+        match parse_line(&line?, i + 1) {
+            Ok((key, feedback)) => {
+                let expected = FeedBack::evaluate(&key.0, &key.1, &HashMap::new());
+                if feedback != expected {
+                    problems.push(CacheError::Malformed {
+                        line: i + 1,
+                        reason: format!("wrong feedback for {},{}", key.0, key.1),
+                    });
+                }
+                cache.insert(key);
+            }
+            Err(e) => problems.push(e),
+        }
+    }
+    let missing = dict
+        .iter()
+        .flat_map(|word| dict.iter().map(move |solution| (word, solution)))
+        .filter(|(word, solution)| !cache.contains(&(word.to_string(), solution.to_string())))
+        .count();
+    if missing > 0 {
+        problems.push(CacheError::Incomplete { missing });
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wordle-bot-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn rows_are_the_same_on_any_number_of_workers() {
+        let dict = words(&["crane", "slate", "pious", "trace", "plate"]);
+        let rows = Mutex::new(Vec::new());
+        let parallel = generate_cache(&dict, 3, |done| rows.lock().unwrap().push(done)).unwrap();
+        assert_eq!(parallel, generate_cache(&dict, 1, |_| {}).unwrap());
+        assert_eq!(parallel.len(), 25);
+        // Every row is reported once, whichever worker computed it
+        let mut rows = rows.into_inner().unwrap();
+        rows.sort();
+        assert_eq!(rows, [1, 2, 3, 4, 5]);
+        assert!(worker_count(0) >= 1);
+        assert_eq!(worker_count(3), 3);
+    }
+
+    #[test]
+    fn complete_adds_only_the_rows_missing() {
+        let mut cache = generate_cache(&words(&["crane", "slate"]), 2, |_| {}).unwrap();
+        assert_eq!(cache.len(), 4);
+        let dict = words(&["crane", "slate", "pious"]);
+        assert_eq!(missing_rows(&cache, &dict).len(), 3);
+        assert_eq!(complete_cache(&mut cache, &dict, 2, |_, _| {}).unwrap(), 3);
+        assert_eq!(cache.len(), 9);
+        assert_eq!(
+            cache[&("pious".to_string(), "slate".to_string())],
+            FeedBack::compute("pious", "slate")
+        );
+        assert_eq!(complete_cache(&mut cache, &dict, 2, |_, _| {}).unwrap(), 0);
+    }
+
+    #[test]
+    fn a_worker_panicking_fails_the_table() {
+        let dict = words(&["crane", "slate", "pious"]);
+        let result = generate_cache(&dict, 2, |done| assert!(done < 2, "row {}", done));
+        assert!(matches!(result, Err(Error::Search(_))));
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let dict = words(&["crane", "slate", "pious"]);
+        let cache = generate_cache(&dict, 1, |_| {}).unwrap();
+        let path = temp_path("round-trip.csv");
+        export_cache(&cache, &path).unwrap();
+        let imported = import_cache(&path).unwrap();
+        let problems = verify_cache(&path, &dict).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(imported, cache);
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn verify_reports_every_problem() {
+        let dict = words(&["crane", "slate"]);
+        let path = temp_path("verify.csv");
+        fs::write(
+            &path,
+            "crane,crane,ggggg\ncrane,slate,bbbbb\nslate,crane\nslate,slate,ggxgg\n",
+        )
+        .unwrap();
+        let problems = verify_cache(&path, &dict).unwrap();
+        let import = import_cache(&path);
+        fs::remove_file(path).unwrap();
+        let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            problems,
+            [
+                "line 2: wrong feedback for crane,slate",
+                "line 3: expected 3 fields in \"slate,crane\"",
+                "line 4: invalid feedback 'x'",
+                "2 word pairs of the dictionary are missing",
+            ]
+        );
+        // Reading the cache stops at the first of them
+        assert!(matches!(import, Err(CacheError::Malformed { line: 3, .. })));
+    }
+
+    #[test]
+    fn load_table_rebuilds_a_corrupted_file() {
+        let dict = words(&["crane", "slate"]);
+        let path = temp_path("corrupted.csv");
+        fs::write(&path, "crane,slate\n").unwrap();
+        let cache = load_table(&path, &dict, 1, Verbosity::Quiet).unwrap();
+        let problems = verify_cache(&path, &dict).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(cache.len(), 4);
+        assert!(problems.is_empty());
+    }
+}
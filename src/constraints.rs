@@ -0,0 +1,407 @@
+use crate::error::{Error, Result};
+use crate::guess::FeedBack;
+use crate::word::Alphabet;
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// How the words matching a query are listed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueryOrder {
+    /// Most likely answers first
+    Weight,
+    Alphabetical,
+}
+
+impl FromStr for QueryOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "weight" => Ok(QueryOrder::Weight),
+            "alphabetical" => Ok(QueryOrder::Alphabetical),
+            _ => Err(Error::InvalidInput(format!(
+                "unknown order {:?}, use weight or alphabetical",
+                s
+            ))),
+        }
+    }
+}
+
+/// What the feedback received so far says about the answer, letter by letter.
+///
+/// Holds for the feedback of [`FeedBack::compute`], where a letter of the
+/// answer is yellow at every other position it is played at, as well as for
+/// Wordle's, where a repeated letter beyond the answer's count is black.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Constraints {
+    /// Letter known at each position
+    pub fixed: Vec<Option<char>>,
+    /// Letters known not to be at each position
+    pub forbidden: Vec<Vec<char>>,
+    /// Least number of times a letter is in the answer
+    pub min_count: BTreeMap<char, usize>,
+    /// Most number of times a letter is in the answer, when known
+    pub max_count: BTreeMap<char, usize>,
+    /// Letters not in the answer, in the order they were found
+    pub excluded: Vec<char>,
+}
+
+impl Constraints {
+    /// No constraint yet on a word of `length` letters.
+    pub fn new(length: usize) -> Self {
+        Self {
+            fixed: vec![None; length],
+            forbidden: vec![Vec::new(); length],
+            ..Self::default()
+        }
+    }
+    /// Constraints of every play of `history`, each a word and its feedback.
+    pub fn from_history(length: usize, history: &[(String, Vec<FeedBack>)]) -> Result<Self> {
+        let mut constraints = Self::new(length);
+        for (word, feedback) in history {
+            constraints.add(word, feedback)?;
+        }
+        Ok(constraints)
+    }
+    /// Constraints of a pattern query.
+    ///
+    /// `pattern` holds the letter at each position, `?`, `_` or `.` where it
+    /// is unknown, such as `?r??e`. The word has the letters of `has`, a letter
+    /// given twice appearing twice, none of `without`, and none of the letters
+    /// of each `not_at` entry at its 1-based position, given as `2:ae`.
+    /// Letters are normalized by `alphabet`, as the dictionary words are.
+    pub fn query(
+        pattern: &str,
+        has: &str,
+        without: &str,
+        not_at: &[String],
+        alphabet: &Alphabet,
+        fold_accents: bool,
+    ) -> Result<Self> {
+        let normalize = |text: &str| -> Result<Vec<char>> {
+            let word = alphabet.normalize(text, fold_accents).ok_or_else(|| {
+                Error::InvalidInput(format!("{:?} has letters outside the language", text))
+            })?;
+            Ok(word.chars().collect())
+        };
+        // Wildcards first, so the letters between them are normalized alone
+        let mut fixed = Vec::new();
+        for c in pattern.chars() {
+            match c {
+                '?' | '_' | '.' => fixed.push(None),
+                c => fixed.extend(normalize(&c.to_string())?.into_iter().map(Some)),
+            }
+        }
+        let mut constraints = Self::new(fixed.len());
+        constraints.fixed = fixed;
+        for c in normalize(has)? {
+            *constraints.min_count.entry(c).or_insert(0) += 1;
+        }
+        for c in normalize(without)? {
+            if constraints.min_count.contains_key(&c) || constraints.fixed.contains(&Some(c)) {
+                return Err(Error::InvalidInput(format!(
+                    "{:?} is both in the word and not in it",
+                    c
+                )));
+            }
+            if !constraints.excluded.contains(&c) {
+                constraints.excluded.push(c);
+            }
+            constraints.max_count.insert(c, 0);
+        }
+        for entry in not_at {
+            let (position, letters) = entry
+                .split_once(':')
+                .and_then(|(position, letters)| Some((position.parse::<usize>().ok()?, letters)))
+                .ok_or_else(|| {
+                    Error::Parse(format!("expected position:letters, got {:?}", entry))
+                })?;
+            if position == 0 || position > constraints.fixed.len() {
+                return Err(Error::InvalidInput(format!(
+                    "position {} is not within the {} letters of {:?}",
+                    position,
+                    constraints.fixed.len(),
+                    pattern
+                )));
+            }
+            for c in normalize(letters)? {
+                constraints.forbid(position - 1, c);
+            }
+        }
+        Ok(constraints)
+    }
+    /// Learn from `feedback` for `word`.
+    ///
+    /// Fails when `word` or `feedback` is not as long as the answer.
+    pub fn add(&mut self, word: &str, feedback: &[FeedBack]) -> Result<()> {
+        let length = word.chars().count();
+        if length != self.fixed.len() || feedback.len() != self.fixed.len() {
+            return Err(Error::InvalidInput(format!(
+                "{:?} with {} feedback tiles does not fit a word of {} letters",
+                word,
+                feedback.len(),
+                self.fixed.len()
+            )));
+        }
+        let mut greens: BTreeMap<char, usize> = BTreeMap::new();
+        let mut yellows: BTreeMap<char, usize> = BTreeMap::new();
+        let mut blacks: Vec<char> = Vec::new();
+        for (j, (c, &fb)) in word.chars().zip(feedback).enumerate() {
+            match fb {
+                FeedBack::Green => {
+                    self.fixed[j] = Some(c);
+                    *greens.entry(c).or_insert(0) += 1;
+                }
+                FeedBack::Yellow => {
+                    self.forbid(j, c);
+                    *yellows.entry(c).or_insert(0) += 1;
+                }
+                FeedBack::Black => {
+                    self.forbid(j, c);
+                    blacks.push(c);
+                }
+            }
+        }
+        for &c in greens.keys().chain(yellows.keys()) {
+            // A yellow may point at a letter of the answer already green, or
+            // at the same one as another yellow, so yellows only prove one
+            let found = greens
+                .get(&c)
+                .copied()
+                .unwrap_or(0)
+                .max(yellows.contains_key(&c) as usize);
+            let min = self.min_count.entry(c).or_insert(0);
+            *min = (*min).max(found);
+        }
+        for c in blacks {
+            let found =
+                greens.get(&c).copied().unwrap_or(0) + yellows.get(&c).copied().unwrap_or(0);
+            if found == 0 {
+                if !self.excluded.contains(&c) {
+                    self.excluded.push(c);
+                }
+                self.max_count.insert(c, 0);
+            } else {
+                let max = self.max_count.entry(c).or_insert(found);
+                *max = (*max).min(found);
+            }
+        }
+        Ok(())
+    }
+    fn forbid(&mut self, position: usize, c: char) {
+        if !self.forbidden[position].contains(&c) {
+            self.forbidden[position].push(c);
+        }
+    }
+    /// Whether `word` can still be the answer.
+    pub fn allows(&self, word: &str) -> bool {
+        let letters: Vec<char> = word.chars().collect();
+        if letters.len() != self.fixed.len() {
+            return false;
+        }
+        for (j, &c) in letters.iter().enumerate() {
+            if self.fixed[j].is_some_and(|fixed| fixed != c) || self.forbidden[j].contains(&c) {
+                return false;
+            }
+        }
+        let count = |c: char| letters.iter().filter(|&&l| l == c).count();
+        self.min_count.iter().all(|(&c, &min)| count(c) >= min)
+            && self.max_count.iter().all(|(&c, &max)| count(c) <= max)
+    }
+    /// The words of `dict` still allowed, with their weight, in `order`,
+    /// keeping the first `limit`. Returns how many there are before the limit too.
+    pub fn matches(
+        &self,
+        dict: &[String],
+        weights: &[f32],
+        order: QueryOrder,
+        limit: Option<usize>,
+    ) -> (usize, Vec<(String, f32)>) {
+        let mut matches: Vec<(String, f32)> = dict
+            .iter()
+            .zip(weights)
+            .filter(|(word, _)| self.allows(word))
+            .map(|(word, &weight)| (word.clone(), weight))
+            .collect();
+        match order {
+            QueryOrder::Weight => matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0))),
+            QueryOrder::Alphabetical => matches.sort_by(|a, b| a.0.cmp(&b.0)),
+        }
+        let total = matches.len();
+        matches.truncate(limit.unwrap_or(total));
+        (total, matches)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.fixed.iter().all(Option::is_none)
+            && self.forbidden.iter().all(Vec::is_empty)
+            && self.min_count.is_empty()
+            && self.max_count.is_empty()
+    }
+}
+
+/// Such as `known: _R__E, has A (not pos 1), no S T N`.
+impl fmt::Display for Constraints {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let upper = |c: char| c.to_uppercase().collect::<String>();
+        let known: String = self
+            .fixed
+            .iter()
+            .map(|c| c.map_or("_".to_string(), upper))
+            .collect();
+        let mut parts = vec![format!("known: {}", known)];
+        for (&c, &min) in &self.min_count {
+            let fixed = self.fixed.iter().filter(|&&l| l == Some(c)).count();
+            let positions: Vec<String> = self
+                .forbidden
+                .iter()
+                .enumerate()
+                .filter(|(_, letters)| letters.contains(&c))
+                .map(|(j, _)| (j + 1).to_string())
+                .collect();
+            if min <= fixed && positions.is_empty() {
+                continue;
+            }
+            let mut part = format!("has {}", upper(c));
+            if min > 1 {
+                part += &format!(" x{}", min);
+            }
+            if !positions.is_empty() {
+                part += &format!(" (not pos {})", positions.join(" "));
+            }
+            parts.push(part);
+        }
+        for (&c, &max) in self.max_count.iter().filter(|(_, &max)| max > 0) {
+            parts.push(format!("at most {} {}", max, upper(c)));
+        }
+        if !self.excluded.is_empty() {
+            let excluded: Vec<String> = self.excluded.iter().map(|&c| upper(c)).collect();
+            parts.push(format!("no {}", excluded.join(" ")));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word::Language;
+
+    fn query(pattern: &str, has: &str, without: &str, not_at: &[&str]) -> Result<Constraints> {
+        let not_at: Vec<String> = not_at.iter().map(|entry| entry.to_string()).collect();
+        let alphabet = Alphabet::for_language(Language::English);
+        Constraints::query(pattern, has, without, &not_at, &alphabet, true)
+    }
+
+    fn play(word: &str, pattern: &str) -> (String, Vec<FeedBack>) {
+        (word.to_string(), FeedBack::parse(pattern).unwrap())
+    }
+
+    #[test]
+    fn history_fixes_forbids_and_excludes_letters() {
+        let constraints = Constraints::from_history(5, &[play("crane", "bgybb")]).unwrap();
+        assert_eq!(constraints.fixed[1], Some('r'));
+        assert_eq!(constraints.forbidden[2], ['a']);
+        assert_eq!(constraints.min_count[&'a'], 1);
+        assert_eq!(constraints.excluded, ['c', 'n', 'e']);
+        assert_eq!(
+            constraints.to_string(),
+            "known: _R___, has A (not pos 3), no C N E"
+        );
+        assert!(constraints.allows("array"));
+        assert!(!constraints.allows("brand"));
+        assert!(!constraints.allows("arrays"));
+    }
+
+    #[test]
+    fn black_repeated_letter_caps_its_count() {
+        // Wordle's feedback for "speed" when the answer has a single "e"
+        let constraints = Constraints::from_history(5, &[play("speed", "bbgbb")]).unwrap();
+        assert_eq!(constraints.max_count[&'e'], 1);
+        assert!(constraints.allows("tweak"));
+        assert!(!constraints.allows("sheep"));
+    }
+
+    #[test]
+    fn add_rejects_a_word_of_another_length() {
+        let mut constraints = Constraints::new(5);
+        assert!(constraints
+            .add("cranes", &FeedBack::parse("bbbbbb").unwrap())
+            .is_err());
+        assert!(constraints
+            .add("crane", &FeedBack::parse("bbbb").unwrap())
+            .is_err());
+        assert!(constraints.is_empty());
+    }
+
+    #[test]
+    fn query_reads_pattern_letters_and_positions() {
+        let constraints = query("?R??E", "aa", "st", &["1:b"]).unwrap();
+        assert_eq!(constraints.fixed, [None, Some('r'), None, None, Some('e')]);
+        assert_eq!(constraints.min_count[&'a'], 2);
+        assert_eq!(constraints.excluded, ['s', 't']);
+        assert!(constraints.allows("arace"));
+        assert!(!constraints.allows("brake"));
+        assert!(!constraints.allows("arise"));
+    }
+
+    #[test]
+    fn query_rejects_contradictions_and_bad_positions() {
+        assert!(query("?r??e", "", "r", &[]).is_err());
+        assert!(query("?r??e", "a", "a", &[]).is_err());
+        assert!(query("?r??e", "", "", &["6:a"]).is_err());
+        assert!(query("?r??e", "", "", &["a"]).is_err());
+        assert!(query("?r?#e", "", "", &[]).is_err());
+    }
+
+    #[test]
+    fn matches_are_sorted_and_limited() {
+        let dict: Vec<String> = ["crane", "trace", "brace", "slate"]
+            .map(String::from)
+            .to_vec();
+        let weights = [1.0, 3.0, 1.0, 5.0];
+        let constraints = query("???ce", "", "", &[]).unwrap();
+        let (total, matches) = constraints.matches(&dict, &weights, QueryOrder::Weight, None);
+        assert_eq!(total, 2);
+        assert_eq!(
+            matches,
+            [("trace".to_string(), 3.0), ("brace".to_string(), 1.0)]
+        );
+        let (total, matches) =
+            constraints.matches(&dict, &weights, QueryOrder::Alphabetical, Some(1));
+        assert_eq!(total, 2);
+        assert_eq!(matches, [("brace".to_string(), 1.0)]);
+        assert_eq!(
+            "alphabetical".parse::<QueryOrder>().unwrap(),
+            QueryOrder::Alphabetical
+        );
+        assert!("random".parse::<QueryOrder>().is_err());
+    }
+
+    #[test]
+    fn query_folds_accents_like_the_dictionary() {
+        let constraints = query("_É_È_", "Ç", "", &["1:Â"]).unwrap();
+        assert_eq!(constraints.fixed, [None, Some('e'), None, Some('e'), None]);
+        assert_eq!(constraints.min_count[&'c'], 1);
+        assert_eq!(constraints.forbidden[0], ['a']);
+    }
+
+    #[test]
+    fn query_of_the_feedback_finds_the_words_the_history_allows() {
+        let dict: Vec<String> = ["array", "bread", "friar", "brand", "ultra", "sugar"]
+            .map(String::from)
+            .to_vec();
+        let weights = [1.0; 6];
+        let history = Constraints::from_history(5, &[play("crane", "bgybb")]).unwrap();
+        let query = query(".r...", "a", "cne", &["3:a"]).unwrap();
+        assert_eq!(query.to_string(), history.to_string());
+        let (total, matches) = query.matches(&dict, &weights, QueryOrder::Alphabetical, None);
+        assert_eq!(total, 2);
+        assert_eq!(
+            matches,
+            history
+                .matches(&dict, &weights, QueryOrder::Alphabetical, None)
+                .1
+        );
+        assert_eq!(matches[0].0, "array");
+        assert_eq!(matches[1].0, "friar");
+    }
+}
@@ -0,0 +1,349 @@
+use crate::error::{Error, Result};
+use crate::word::{self, Alphabet};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Number of rejected words kept as examples for each reason.
+const EXAMPLES: usize = 5;
+
+/// How word lists are turned into a dictionary.
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    /// Number of letters of every word
+    pub length: usize,
+    pub alphabet: Alphabet,
+    /// Fold accented letters to their base letter, see [`Alphabet::normalize`]
+    pub fold_accents: bool,
+    /// Lowercase words with capitals instead of rejecting them as proper nouns
+    pub fold_case: bool,
+}
+
+/// Why a line of a word list did not make it into the dictionary.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rejection {
+    /// Contains a capital letter and case folding is off
+    ProperNoun,
+    /// Contains a letter outside the alphabet
+    InvalidLetter,
+    WrongLength,
+    /// Already loaded, possibly spelled differently before normalization
+    Duplicate,
+    /// The weight column is not a positive number
+    InvalidWeight,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Rejection::ProperNoun => "proper noun",
+            Rejection::InvalidLetter => "invalid letter",
+            Rejection::WrongLength => "wrong length",
+            Rejection::Duplicate => "duplicate",
+            Rejection::InvalidWeight => "invalid weight",
+        })
+    }
+}
+
+/// What happened to the lines of the word lists.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    pub files: Vec<PathBuf>,
+    /// Lines holding a word, comments and blank lines aside
+    pub lines: usize,
+    pub accepted: usize,
+    /// Number of words rejected for each reason
+    pub rejected: BTreeMap<Rejection, usize>,
+    /// First few words rejected for each reason
+    pub examples: BTreeMap<Rejection, Vec<String>>,
+}
+
+impl Report {
+    fn reject(&mut self, reason: Rejection, word: &str) {
+        *self.rejected.entry(reason).or_insert(0) += 1;
+        let examples = self.examples.entry(reason).or_default();
+        if examples.len() < EXAMPLES {
+            examples.push(word.to_string());
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} words loaded from {} lines",
+            self.accepted, self.lines
+        )?;
+        if self.rejected.is_empty() {
+            return Ok(());
+        }
+        let rejected: Vec<String> = self
+            .rejected
+            .iter()
+            .map(|(reason, count)| format!("{} {}", count, reason))
+            .collect();
+        write!(f, ", rejected {}", rejected.join(", "))
+    }
+}
+
+/// A list of words of equal length with the prior weight of each.
+#[derive(Clone, Debug)]
+pub struct Dictionary {
+    pub words: Vec<String>,
+    /// Prior weights, when the word lists had a weight column
+    pub weights: Option<Vec<f32>>,
+    pub report: Report,
+}
+
+/// Read and merge the word lists at `paths`.
+///
+/// Each line holds a word, optionally followed by its weight after a comma
+/// or whitespace. Everything after a `#` is a comment. Words are normalized
+/// by [`Alphabet::normalize`] and only their first occurrence is kept.
+pub fn load(paths: &[PathBuf], options: &LoadOptions) -> Result<Dictionary> {
+    let mut report = Report {
+        files: paths.to_vec(),
+        ..Report::default()
+    };
+    let mut words = Vec::new();
+    let mut raw_weights = Vec::new();
+    let mut seen = HashSet::new();
+    for path in paths {
+        let text = fs::read_to_string(path)
+            .map_err(|e| Error::Dictionary(format!("cannot read {}: {}", path.display(), e)))?;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let (raw, weight) = match split_fields(line) {
+                Some(fields) => fields,
+                None => continue,
+            };
+            report.lines += 1;
+            if !options.fold_case && raw.chars().any(char::is_uppercase) {
+                report.reject(Rejection::ProperNoun, raw);
+                continue;
+            }
+            let word = match options.alphabet.normalize(raw, options.fold_accents) {
+                Some(word) => word,
+                None => {
+                    report.reject(Rejection::InvalidLetter, raw);
+                    continue;
+                }
+            };
+            if word::len(&word) != options.length {
+                report.reject(Rejection::WrongLength, raw);
+                continue;
+            }
+            let weight = match weight.map(parse_weight) {
+                Some(None) => {
+                    report.reject(Rejection::InvalidWeight, raw);
+                    continue;
+                }
+                Some(Some(weight)) => Some(weight),
+                None => None,
+            };
+            if !seen.insert(word.clone()) {
+                report.reject(Rejection::Duplicate, raw);
+                continue;
+            }
+            words.push(word);
+            raw_weights.push(weight);
+        }
+    }
+    report.accepted = words.len();
+    if words.is_empty() {
+        let files: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+        return Err(Error::Dictionary(format!(
+            "{} has no words of {} letters ({})",
+            files.join(", "),
+            options.length,
+            report
+        )));
+    }
+    let weights = if raw_weights.iter().any(Option::is_some) {
+        Some(scale_weights(&raw_weights).ok_or_else(|| {
+            Error::Dictionary("the weight column has no positive weight".to_string())
+        })?)
+    } else {
+        None
+    };
+    Ok(Dictionary {
+        words,
+        weights,
+        report,
+    })
+}
+
+/// The word and the optional weight of a line, `None` for a blank line.
+fn split_fields(line: &str) -> Option<(&str, Option<&str>)> {
+    let mut fields = line
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|field| !field.is_empty());
+    fields.next().map(|word| (word, fields.next()))
+}
+
+fn parse_weight(weight: &str) -> Option<f32> {
+    weight
+        .parse::<f32>()
+        .ok()
+        .filter(|w| w.is_finite() && *w >= 0.0)
+}
+
+/// Give missing weights the smallest positive weight found, rather than none
+/// so the words stay possible answers, and scale the weights to a mean of 1.
+fn scale_weights(raw: &[Option<f32>]) -> Option<Vec<f32>> {
+    let floor = raw
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|&w| w > 0.0)
+        .fold(f32::INFINITY, f32::min);
+    if !floor.is_finite() {
+        return None;
+    }
+    let weights: Vec<f32> = raw.iter().map(|w| w.unwrap_or(floor).max(floor)).collect();
+    let mean = weights.iter().sum::<f32>() / weights.len() as f32;
+    Some(weights.into_iter().map(|w| w / mean).collect())
+}
+
+/// Every word equally likely to be the answer.
+pub fn uniform_weights(dict: &[String]) -> Vec<f32> {
+    vec![1.0; dict.len()]
+}
+
+/// Read the prior weight of each word of `dict` from `path`.
+///
+/// Each line holds a word and a number, separated by a comma or whitespace:
+/// a frequency count or a probability, only their ratios matter. Words
+/// missing from the file get the smallest weight found. Weights are scaled
+/// to a mean of 1. Words are normalized with `options`, as the dictionary
+/// was, and only their first weight is kept.
+pub fn load_weights(path: &Path, dict: &[String], options: &LoadOptions) -> Result<Vec<f32>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Error::Dictionary(format!("cannot read {}: {}", path.display(), e)))?;
+    let mut found = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let (word, weight) = match split_fields(line) {
+            Some((word, Some(weight))) => (word, weight),
+            _ => continue,
+        };
+        let weight = parse_weight(weight).ok_or_else(|| {
+            Error::Parse(format!(
+                "{}:{}: invalid weight {:?}",
+                path.display(),
+                i + 1,
+                weight
+            ))
+        })?;
+        if let Some(word) = options.alphabet.normalize(word, options.fold_accents) {
+            found.entry(word).or_insert(weight);
+        }
+    }
+    let raw: Vec<Option<f32>> = dict.iter().map(|word| found.get(word).copied()).collect();
+    scale_weights(&raw)
+        .ok_or_else(|| Error::Dictionary(format!("{} has no positive weight", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word::Language;
+
+    fn options(fold_accents: bool) -> LoadOptions {
+        LoadOptions {
+            length: 5,
+            alphabet: Alphabet::for_language(Language::French),
+            fold_accents,
+            fold_case: false,
+        }
+    }
+
+    fn write(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("wordle-bot-{}-{}", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_normalizes_and_reports_rejected_words() {
+        let path = write(
+            "words.txt",
+            "étude\ncrane 3\nEtude\nParis\ncranes\netude\nc4ane\n",
+        );
+        let dictionary = load(std::slice::from_ref(&path), &options(true)).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(dictionary.words, ["etude", "crane"]);
+        assert_eq!(dictionary.report.lines, 7);
+        assert_eq!(dictionary.report.accepted, 2);
+        // Words without a weight get the smallest one found
+        assert_eq!(dictionary.weights, Some(vec![1.0, 1.0]));
+    }
+
+    #[test]
+    fn weights_are_matched_to_the_normalized_words() {
+        let path = write("weights.txt", "ÉTUDE,3\ncrane 1 # common\netude 9\n");
+        let folded = load_weights(
+            &path,
+            &["etude".to_string(), "crane".to_string()],
+            &options(true),
+        );
+        let kept = load_weights(
+            &path,
+            &["étude".to_string(), "crane".to_string()],
+            &options(false),
+        );
+        fs::remove_file(path).unwrap();
+        assert_eq!(folded.unwrap(), [1.5, 0.5]);
+        assert_eq!(kept.unwrap(), [1.5, 0.5]);
+    }
+
+    #[test]
+    fn lists_are_merged_with_their_comments_and_weights_aside() {
+        let first = write(
+            "first.txt",
+            "# answers\ncrane,4 # common\n\nslate 0.5\nplate x\n",
+        );
+        let second = write("second.txt", "CRANE\ncrane\npious\nParis\n");
+        let paths = [first, second];
+        let strict = load(&paths, &options(true)).unwrap();
+        let folded = load(
+            &paths,
+            &LoadOptions {
+                fold_case: true,
+                ..options(true)
+            },
+        )
+        .unwrap();
+        let empty = load(
+            &paths,
+            &LoadOptions {
+                length: 6,
+                ..options(true)
+            },
+        );
+        paths.iter().for_each(|path| fs::remove_file(path).unwrap());
+
+        assert_eq!(strict.words, ["crane", "slate", "pious"]);
+        // pious gets the smallest weight, then all are scaled to a mean of 1
+        assert_eq!(strict.weights, Some(vec![2.4, 0.3, 0.3]));
+        let report = &strict.report;
+        assert_eq!(report.files.len(), 2);
+        assert_eq!((report.lines, report.accepted), (7, 3));
+        assert_eq!(report.rejected[&Rejection::ProperNoun], 2);
+        assert_eq!(report.examples[&Rejection::ProperNoun], ["CRANE", "Paris"]);
+        assert_eq!(report.rejected[&Rejection::InvalidWeight], 1);
+        assert_eq!(
+            report.to_string(),
+            "3 words loaded from 7 lines, rejected 2 proper noun, 1 duplicate, 1 invalid weight"
+        );
+        // Folding the case lets the capitals in, where CRANE is then a duplicate
+        assert_eq!(folded.words, ["crane", "slate", "pious", "paris"]);
+        assert_eq!(folded.report.rejected[&Rejection::Duplicate], 2);
+        assert!(matches!(empty, Err(Error::Dictionary(_))));
+    }
+}
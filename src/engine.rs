@@ -0,0 +1,430 @@
+use crate::cache::PatternTable;
+use crate::error::{Error, Result};
+use crate::game::Game;
+use crate::guess::FeedBack;
+use crate::solver::Solver;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Lines, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Number of lines of an engine's stderr kept to tell why it stopped.
+const STDERR_LINES: usize = 5;
+
+/// Limits of a `go` command, such as `go iterations 200 movetime 1000`.
+///
+/// Limits left out fall back to the options the engine was started with.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// Number of iterations per solution
+    pub iterations: Option<usize>,
+    /// Time to spend searching
+    pub movetime: Option<Duration>,
+}
+
+impl Limits {
+    /// Parse the arguments following `go`, as pairs of a limit and its value.
+    pub fn parse(args: &[&str]) -> Result<Self> {
+        let mut limits = Self::default();
+        for pair in args.chunks(2) {
+            let (name, value) = match pair {
+                [name, value] => (*name, *value),
+                _ => return Err(Error::Parse(format!("missing value for {:?}", pair[0]))),
+            };
+            let value: u64 = value
+                .parse()
+                .map_err(|_| Error::Parse(format!("invalid value {:?} for {}", value, name)))?;
+            match name {
+                "iterations" => limits.iterations = Some(value as usize),
+                "movetime" => limits.movetime = Some(Duration::from_millis(value)),
+                _ => return Err(Error::Parse(format!("unknown limit {:?}", name))),
+            }
+        }
+        Ok(limits)
+    }
+    /// The `go` command setting these limits.
+    pub fn command(&self) -> String {
+        let mut command = "go".to_string();
+        if let Some(iterations) = self.iterations {
+            command += &format!(" iterations {}", iterations);
+        }
+        if let Some(movetime) = self.movetime {
+            command += &format!(" movetime {}", movetime.as_millis());
+        }
+        command
+    }
+}
+
+/// Speak the engine protocol on `input` and `output` until `quit` or the end of input.
+///
+/// The protocol is line based, in the spirit of UCI for chess engines:
+///
+/// - `wordle` is answered with `id name ...`, `id words N` and `wordleok`,
+/// - `isready` is answered with `readyok`,
+/// - `newgame` starts a game from the whole dictionary,
+/// - `guess-result crane bgybb` records the feedback of a guess,
+/// - `go [iterations N] [movetime MS]` searches, reports `info` lines and
+///   ends with `bestmove WORD`, or `bestmove (none)` when the search fails,
+/// - `quit` stops the engine.
+///
+/// Problems are reported as `info string error ...` without ending the session.
+pub fn run(solver: &Solver, input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let mut guess = solver.new_game();
+    let mut num_guess = 0;
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => continue,
+            ["wordle"] => {
+                writeln!(output, "id name wordle-bot {}", env!("CARGO_PKG_VERSION"))?;
+                writeln!(output, "id words {}", solver.dict().len())?;
+                writeln!(output, "wordleok")?;
+            }
+            ["isready"] => writeln!(output, "readyok")?,
+            ["newgame"] => {
+                guess = solver.new_game();
+                num_guess = 0;
+            }
+            ["guess-result", word, pattern] => {
+                let refined = FeedBack::parse(pattern)
+                    .and_then(|feedback| solver.refine(&guess, word, &feedback));
+                match refined {
+                    Ok(refined) if refined.num_solutions() > 0 => {
+                        guess = refined;
+                        num_guess += 1;
+                    }
+                    Ok(_) => writeln!(output, "info string error no word matches {}", line)?,
+                    Err(e) => writeln!(output, "info string error {}", e)?,
+                }
+            }
+            ["go", args @ ..] => {
+                let result = Limits::parse(args).and_then(|limits| {
+                    let mut options = solver.options().clone();
+                    options.iterations = limits.iterations.unwrap_or(options.iterations);
+                    options.time_limit = limits.movetime.or(options.time_limit);
+                    solver.search_with(&guess, num_guess, &options)
+                });
+                match result {
+                    Ok(result) => {
+                        writeln!(
+                            output,
+                            "info candidates {} simulations {} time {}",
+                            result.candidates, result.simulations, result.elapsed_ms
+                        )?;
+                        for (i, s) in result.suggestions.iter().enumerate() {
+                            writeln!(
+                                output,
+                                "info rank {} word {} score {:.3} visits {} win {:.3} remaining {:.2}",
+                                i + 1,
+                                s.word,
+                                s.mean_score,
+                                s.visits,
+                                s.win_probability,
+                                s.expected_remaining
+                            )?;
+                        }
+                        writeln!(output, "bestmove {}", result.best().word)?;
+                    }
+                    Err(e) => {
+                        writeln!(output, "info string error {}", e)?;
+                        writeln!(output, "bestmove (none)")?;
+                    }
+                }
+            }
+            ["quit"] => break,
+            _ => writeln!(output, "info string error unknown command {:?}", line)?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// An engine run as a child process, spoken to over its stdin and stdout.
+pub struct Engine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    /// Last lines the engine wrote on stderr
+    stderr: Arc<Mutex<VecDeque<String>>>,
+    /// Thread reading stderr, done once the engine exits
+    stderr_reader: Option<thread::JoinHandle<()>>,
+}
+
+impl Engine {
+    /// Start `command`, split on whitespace, and wait until it is ready.
+    ///
+    /// The engine's stderr is kept out of the referee's output, as search
+    /// progress would drown it, but its last lines are reported if the engine stops.
+    pub fn start(command: &str) -> Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| Error::InvalidInput("empty engine command".to_string()))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                Error::Io(io::Error::new(
+                    e.kind(),
+                    format!("cannot start {:?}: {}", command, e),
+                ))
+            })?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let stderr = Arc::new(Mutex::new(VecDeque::new()));
+        let pipe = child.stderr.take().expect("stderr is piped");
+        let tail = Arc::clone(&stderr);
+        let stderr_reader = thread::spawn(move || {
+            for line in BufReader::new(pipe).lines().map_while(|line| line.ok()) {
+                let mut tail = tail.lock().unwrap();
+                if tail.len() == STDERR_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+        });
+        let mut engine = Self {
+            name: command.to_string(),
+            child,
+            stdin,
+            stdout,
+            stderr,
+            stderr_reader: Some(stderr_reader),
+        };
+        engine.send("wordle")?;
+        loop {
+            let line = engine.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.to_string();
+            } else if line == "wordleok" {
+                return Ok(engine);
+            }
+        }
+    }
+    /// Name the engine gave itself, or its command when it gave none.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    fn send(&mut self, line: &str) -> Result<()> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+    fn read_line(&mut self) -> Result<String> {
+        match self.stdout.next() {
+            Some(line) => Ok(line?),
+            None => {
+                // Let the engine finish writing why it stopped
+                if let Some(reader) = self.stderr_reader.take() {
+                    let _ = reader.join();
+                }
+                let tail = self.stderr.lock().unwrap();
+                let mut message = format!("engine {:?} stopped", self.name);
+                if !tail.is_empty() {
+                    message += &format!(": {}", Vec::from(tail.clone()).join(" / "));
+                }
+                Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    message,
+                )))
+            }
+        }
+    }
+    /// Play `game` to the end, asking the engine for every guess.
+    ///
+    /// Returns why the engine forfeited, such as playing a word the game
+    /// refuses, or `None` when the game was played to the end.
+    pub fn play(
+        &mut self,
+        game: &mut Game,
+        dict: &[String],
+        cache: &PatternTable,
+        limits: &Limits,
+    ) -> Result<Option<String>> {
+        self.send("newgame")?;
+        while !game.is_over() {
+            self.send(&limits.command())?;
+            let word = loop {
+                let line = self.read_line()?;
+                if let Some(word) = line.strip_prefix("bestmove ") {
+                    break word.trim().to_string();
+                }
+            };
+            if word == "(none)" {
+                return Ok(Some("found no word to play".to_string()));
+            }
+            let feedback = match game.play(&word, dict, cache) {
+                Ok(feedback) => feedback,
+                Err(e) => return Ok(Some(format!("played {:?}: {}", word, e))),
+            };
+            let pattern: String = feedback.iter().map(|fb| fb.to_char()).collect();
+            self.send(&format!("guess-result {} {}", word, pattern))?;
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // The engine may be gone already, nothing more can be done about it
+        let _ = self.send("quit");
+        let _ = self.child.wait();
+    }
+}
+
+/// How an engine fared over the games of a [`referee`] run.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Standing {
+    pub name: String,
+    pub played: usize,
+    pub won: usize,
+    /// Guesses summed over the games won
+    pub total_guesses: usize,
+    /// Games lost by playing a word the game refused
+    pub forfeits: usize,
+    /// Games where no other engine needed fewer guesses, losses counting as one guess too many
+    pub best: usize,
+}
+
+impl Standing {
+    /// Mean number of guesses over the games won.
+    pub fn average_guesses(&self) -> f32 {
+        if self.won == 0 {
+            return 0.0;
+        }
+        self.total_guesses as f32 / self.won as f32
+    }
+}
+
+/// Let every engine play against each of `answers`, calling `on_game` with the
+/// games of every answer, one per engine, and why an engine forfeited if it did.
+pub fn referee(
+    engines: &mut [Engine],
+    answers: &[String],
+    dict: &[String],
+    cache: &PatternTable,
+    max_guess: u8,
+    limits: &Limits,
+    mut on_game: impl FnMut(&str, &[(Game, Option<String>)]),
+) -> Result<Vec<Standing>> {
+    let mut standings: Vec<Standing> = engines
+        .iter()
+        .map(|engine| Standing {
+            name: engine.name().to_string(),
+            ..Standing::default()
+        })
+        .collect();
+    for answer in answers {
+        let games = engines
+            .iter_mut()
+            .map(|engine| {
+                let mut game = Game::new(answer, dict, max_guess, false)?;
+                let forfeit = engine.play(&mut game, dict, cache, limits)?;
+                Ok((game, forfeit))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let cost = |game: &Game| {
+            if game.is_won() {
+                game.num_guess()
+            } else {
+                max_guess + 1
+            }
+        };
+        let fewest = games.iter().map(|(game, _)| cost(game)).min().unwrap_or(0);
+        for (standing, (game, forfeit)) in standings.iter_mut().zip(&games) {
+            standing.played += 1;
+            if game.is_won() {
+                standing.won += 1;
+                standing.total_guesses += game.num_guess() as usize;
+            }
+            standing.forfeits += forfeit.is_some() as usize;
+            standing.best += (cost(game) == fewest) as usize;
+        }
+        on_game(answer, &games);
+    }
+    Ok(standings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PatternTable;
+    use crate::mcts::SearchOptions;
+    use crate::progress::Verbosity;
+    use crate::store::tests::temp_store;
+
+    fn solver() -> Solver {
+        let dict: Vec<String> = ["crane", "crate", "trace", "slate", "plate", "pious"]
+            .map(String::from)
+            .to_vec();
+        let weights = vec![1.0; dict.len()];
+        let options = SearchOptions {
+            iterations: 20,
+            threads: 2,
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        Solver::new(dict, weights, PatternTable::new(), temp_store(), options)
+    }
+
+    fn session(solver: &Solver, input: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        run(solver, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn limits_round_trip_through_the_go_command() {
+        let limits = Limits::parse(&["iterations", "200", "movetime", "1000"]).unwrap();
+        assert_eq!(limits.iterations, Some(200));
+        assert_eq!(limits.movetime, Some(Duration::from_millis(1000)));
+        assert_eq!(limits.command(), "go iterations 200 movetime 1000");
+        assert_eq!(Limits::default().command(), "go");
+    }
+
+    #[test]
+    fn limits_reject_unknown_and_incomplete_pairs() {
+        assert!(Limits::parse(&["depth", "3"]).is_err());
+        assert!(Limits::parse(&["iterations"]).is_err());
+        assert!(Limits::parse(&["movetime", "soon"]).is_err());
+    }
+
+    #[test]
+    fn handshake_names_the_engine_and_its_dictionary() {
+        let solver = solver();
+        let lines = session(&solver, "wordle\nisready\nquit\nisready\n");
+        assert!(lines[0].starts_with("id name wordle-bot"));
+        assert_eq!(lines[1..], ["id words 6", "wordleok", "readyok"]);
+    }
+
+    #[test]
+    fn zero_movetime_still_answers_a_word() {
+        let solver = solver();
+        let lines = session(&solver, "newgame\ngo movetime 0\n");
+        let best = lines.last().unwrap();
+        assert!(best.starts_with("bestmove ") && best != "bestmove (none)");
+    }
+
+    #[test]
+    fn errors_are_reported_without_ending_the_session() {
+        let solver = solver();
+        let lines = session(&solver, "guess-result zzzzz bbbbb\nfly\nisready\n");
+        assert!(lines[0].starts_with("info string error"));
+        assert!(lines[1].starts_with("info string error unknown command"));
+        assert_eq!(lines[2], "readyok");
+    }
+}
@@ -0,0 +1,153 @@
+use crate::cache::CacheError;
+use std::{fmt, io};
+
+/// Error of any operation of the crate.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Text that could not be parsed, such as a cache line or a feedback pattern
+    Parse(String),
+    /// The dictionary is unusable or does not contain a word
+    Dictionary(String),
+    /// Input given by the user is not valid for the current game
+    InvalidInput(String),
+    /// The search could not complete
+    Search(String),
+    /// The state store could not be read or written
+    Store(redb::Error),
+    /// The search was stopped by a signal, after saving its progress
+    Interrupted,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Short name of the kind of error, for machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Io(_) => "io",
+            Error::Parse(_) => "parse",
+            Error::Dictionary(_) => "dictionary",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::Search(_) => "search",
+            Error::Store(_) => "store",
+            Error::Interrupted => "interrupted",
+        }
+    }
+    /// Process exit code for the error, following the BSD `sysexits.h` values,
+    /// or the shell's 128 + SIGINT when interrupted.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidInput(_) => 64,
+            Error::Parse(_) => 65,
+            Error::Dictionary(_) => 66,
+            Error::Search(_) => 70,
+            Error::Io(_) | Error::Store(_) => 74,
+            Error::Interrupted => 130,
+        }
+    }
+    /// HTTP status code for the error, as answered by the server.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Error::InvalidInput(_) | Error::Parse(_) | Error::Dictionary(_) => 400,
+            Error::Search(_) | Error::Io(_) | Error::Store(_) => 500,
+            Error::Interrupted => 503,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse(msg) => write!(f, "parse error: {}", msg),
+            Error::Dictionary(msg) => write!(f, "dictionary error: {}", msg),
+            Error::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
+            Error::Search(msg) => write!(f, "search failed: {}", msg),
+            Error::Store(e) => write!(f, "state store error: {}", e),
+            Error::Interrupted => write!(f, "interrupted, the search so far is saved"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<CacheError> for Error {
+    fn from(e: CacheError) -> Self {
+        match e {
+            CacheError::Io(e) => Error::Io(e),
+            e => Error::Parse(e.to_string()),
+        }
+    }
+}
+
+macro_rules! from_store_error {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Error {
+            fn from(e: $t) -> Self {
+                Error::Store(e.into())
+            }
+        })*
+    };
+}
+
+from_store_error!(
+    redb::Error,
+    redb::DatabaseError,
+    redb::TransactionError,
+    redb::TableError,
+    redb::StorageError,
+    redb::CommitError
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_kind_of_error_has_its_own_exit_code() {
+        let errors = [
+            Error::Io(io::Error::other("disk full")),
+            Error::Parse(String::new()),
+            Error::Dictionary(String::new()),
+            Error::InvalidInput(String::new()),
+            Error::Search(String::new()),
+            Error::Interrupted,
+        ];
+        let codes: Vec<i32> = errors.iter().map(Error::exit_code).collect();
+        assert_eq!(codes, [74, 65, 66, 64, 70, 130]);
+        let statuses: Vec<u16> = errors.iter().map(Error::http_status).collect();
+        assert_eq!(statuses, [500, 400, 400, 400, 500, 503]);
+        let kinds: Vec<&str> = errors.iter().map(Error::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                "io",
+                "parse",
+                "dictionary",
+                "invalid_input",
+                "search",
+                "interrupted"
+            ]
+        );
+    }
+
+    #[test]
+    fn cache_errors_keep_io_apart_from_parsing() {
+        let io = Error::from(CacheError::Io(io::Error::other("disk full")));
+        assert!(matches!(io, Error::Io(_)));
+        let malformed = Error::from(CacheError::Malformed {
+            line: 3,
+            reason: "bad".to_string(),
+        });
+        assert_eq!(malformed.to_string(), "parse error: line 3: bad");
+        let store = Error::from(redb::Error::DatabaseAlreadyOpen);
+        assert_eq!(store.exit_code(), 74);
+    }
+}
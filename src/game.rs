@@ -0,0 +1,342 @@
+use crate::cache::PatternTable;
+use crate::error::{Error, Result};
+use crate::guess::FeedBack;
+use crate::solver::Solver;
+use crate::word;
+use rand::{seq::SliceRandom, thread_rng};
+use std::io::BufRead;
+
+/// One guess of a game and the feedback it got.
+#[derive(Clone, Debug)]
+pub struct Play {
+    pub word: String,
+    pub feedback: Vec<FeedBack>,
+}
+
+/// The referee of one game: knows the answer and enforces the rules.
+#[derive(Clone, Debug)]
+pub struct Game {
+    secret: String,
+    max_guess: u8,
+    hard_mode: bool,
+    history: Vec<Play>,
+}
+
+impl Game {
+    /// A game whose answer is `secret`, which must be a word of `dict`.
+    pub fn new(secret: &str, dict: &[String], max_guess: u8, hard_mode: bool) -> Result<Self> {
+        if !dict.iter().any(|w| w == secret) {
+            return Err(Error::InvalidInput(format!(
+                "answer {:?} is not in the dictionary",
+                secret
+            )));
+        }
+        Ok(Self {
+            secret: secret.to_string(),
+            max_guess,
+            hard_mode,
+            history: Vec::new(),
+        })
+    }
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+    pub fn max_guess(&self) -> u8 {
+        self.max_guess
+    }
+    pub fn hard_mode(&self) -> bool {
+        self.hard_mode
+    }
+    /// Guesses played so far, in order.
+    pub fn history(&self) -> &[Play] {
+        &self.history
+    }
+    /// Feedback of each guess, in order, as laid out in a share grid.
+    pub fn rows(&self) -> Vec<Vec<FeedBack>> {
+        self.history
+            .iter()
+            .map(|play| play.feedback.clone())
+            .collect()
+    }
+    pub fn num_guess(&self) -> u8 {
+        self.history.len() as u8
+    }
+    pub fn is_won(&self) -> bool {
+        self.history
+            .last()
+            .is_some_and(|play| play.word == self.secret)
+    }
+    pub fn is_over(&self) -> bool {
+        self.is_won() || self.num_guess() >= self.max_guess
+    }
+    /// Check `word` may be played now, without playing it.
+    ///
+    /// In hard mode, letters found so far must be played again: green ones
+    /// at the same position, yellow ones anywhere.
+    pub fn check(&self, word: &str, dict: &[String]) -> Result<()> {
+        if self.is_over() {
+            return Err(Error::InvalidInput("the game is over".to_string()));
+        }
+        if word::len(word) != word::len(&self.secret) {
+            return Err(Error::InvalidInput(format!(
+                "{:?} does not have {} letters",
+                word,
+                word::len(&self.secret)
+            )));
+        }
+        if !dict.iter().any(|w| w == word) {
+            return Err(Error::InvalidInput(format!(
+                "{:?} is not in the word list",
+                word
+            )));
+        }
+        if !self.hard_mode {
+            return Ok(());
+        }
+        let letters: Vec<char> = word.chars().collect();
+        for play in &self.history {
+            for (i, (c, &fb)) in play.word.chars().zip(&play.feedback).enumerate() {
+                match fb {
+                    FeedBack::Green if letters[i] != c => {
+                        return Err(Error::InvalidInput(format!(
+                            "hard mode: letter {} must be {}",
+                            i + 1,
+                            c
+                        )))
+                    }
+                    FeedBack::Yellow if !letters.contains(&c) => {
+                        return Err(Error::InvalidInput(format!(
+                            "hard mode: the guess must contain {}",
+                            c
+                        )))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Play `word` and return its feedback, once [`Game::check`] passes.
+    pub fn play(
+        &mut self,
+        word: &str,
+        dict: &[String],
+        cache: &PatternTable,
+    ) -> Result<Vec<FeedBack>> {
+        self.check(word, dict)?;
+        let feedback = FeedBack::evaluate(word, &self.secret, cache);
+        self.history.push(Play {
+            word: word.to_string(),
+            feedback: feedback.clone(),
+        });
+        Ok(feedback)
+    }
+}
+
+/// Draw `count` different answers from `dict`, the way the prior `weights` say
+/// answers are drawn.
+pub fn draw_answers(dict: &[String], weights: &[f32], count: usize) -> Result<Vec<String>> {
+    let indices: Vec<usize> = (0..dict.len()).collect();
+    Ok(indices
+        .choose_multiple_weighted(&mut thread_rng(), count, |&i| weights[i])
+        .map_err(|e| Error::InvalidInput(format!("cannot draw answers: {}", e)))?
+        .map(|&i| dict[i].clone())
+        .collect())
+}
+
+/// Read the lines of `input`, calling `prompt` before each, and pass them
+/// trimmed to `play` until `is_over`. Returns whether the game got there
+/// before the input ended.
+///
+/// Keep playing after a typo instead of throwing the game away: a line `play`
+/// rejects goes to `on_error` and the next one is read.
+pub fn play_lines<G>(
+    game: &mut G,
+    input: impl BufRead,
+    is_over: impl Fn(&G) -> bool,
+    mut prompt: impl FnMut(&G),
+    mut play: impl FnMut(&mut G, &str) -> Result<()>,
+    mut on_error: impl FnMut(&Error),
+) -> Result<bool> {
+    let mut lines = input.lines();
+    while !is_over(game) {
+        prompt(game);
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(false),
+        };
+        if let Err(e) = play(game, line.trim()) {
+            on_error(&e);
+        }
+    }
+    Ok(true)
+}
+
+/// The word and feedback of each of `entries`, given as `word:pattern`, or
+/// as a word whose feedback is computed from `answer` when there is one.
+///
+/// Words are normalized like the dictionary, and checked against the rules
+/// of a game of `max_guess` guesses when the answer is known.
+pub fn read_plays(
+    solver: &Solver,
+    answer: Option<&str>,
+    entries: &[String],
+    max_guess: u8,
+) -> Result<Vec<(String, Vec<FeedBack>)>> {
+    let mut game = answer
+        .map(|answer| Game::new(&solver.normalize(answer), solver.dict(), max_guess, false))
+        .transpose()?;
+    entries
+        .iter()
+        .map(|entry| {
+            let (word, feedback) = match entry.split_once(':') {
+                Some((word, pattern)) => (word, Some(FeedBack::parse(pattern)?)),
+                None => (entry.as_str(), None),
+            };
+            let word = solver.normalize(word);
+            let feedback = match (&mut game, feedback) {
+                (Some(game), given) => {
+                    let feedback = game.play(&word, solver.dict(), solver.cache())?;
+                    if given.is_some_and(|given| given != feedback) {
+                        return Err(Error::InvalidInput(format!(
+                            "the feedback given for {} does not match the answer",
+                            word
+                        )));
+                    }
+                    feedback
+                }
+                (None, Some(feedback)) => feedback,
+                (None, None) => {
+                    return Err(Error::InvalidInput(format!(
+                        "give the feedback of {} as {}:pattern, or the answer with --answer",
+                        word, word
+                    )))
+                }
+            };
+            Ok((word, feedback))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::SearchOptions;
+    use crate::store::tests::temp_store;
+
+    fn dict() -> Vec<String> {
+        ["crane", "crate", "trace", "slate", "plate"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn hard_mode_keeps_the_letters_found() {
+        let mut dict = dict();
+        dict.push("pious".to_string());
+        let cache = PatternTable::new();
+        let mut game = Game::new("slate", &dict, 6, true).unwrap();
+        assert_eq!(
+            game.play("pious", &dict, &cache).unwrap(),
+            FeedBack::parse("bbbby").unwrap()
+        );
+        // The s found has to be played again
+        assert!(game.check("crane", &dict).is_err());
+        assert!(game.check("slate", &dict).is_ok());
+        let mut game = Game::new("slate", &dict, 6, true).unwrap();
+        game.play("plate", &dict, &cache).unwrap();
+        // So do the greens, at their place
+        assert!(game.check("crate", &dict).is_err());
+        assert!(game.check("slate", &dict).is_ok());
+        // The same plays are fine outside hard mode
+        let mut easy = Game::new("slate", &dict, 6, false).unwrap();
+        easy.play("pious", &dict, &cache).unwrap();
+        assert!(easy.check("crane", &dict).is_ok());
+    }
+
+    #[test]
+    fn plays_follow_the_rules_of_the_game() {
+        let dict = dict();
+        let cache = PatternTable::new();
+        assert!(Game::new("pious", &dict, 6, false).is_err());
+        let mut game = Game::new("slate", &dict, 2, false).unwrap();
+        assert!(game.play("crates", &dict, &cache).is_err());
+        assert!(game.play("zzzzz", &dict, &cache).is_err());
+        game.play("crane", &dict, &cache).unwrap();
+        game.play("plate", &dict, &cache).unwrap();
+        assert!(game.is_over() && !game.is_won());
+        assert!(game.play("slate", &dict, &cache).is_err());
+        assert_eq!(game.rows().len(), 2);
+    }
+
+    #[test]
+    fn play_lines_reports_typos_and_stops_once_over() {
+        let dict = dict();
+        let cache = PatternTable::new();
+        let mut game = Game::new("slate", &dict, 6, false).unwrap();
+        let mut errors = Vec::new();
+        let finished = play_lines(
+            &mut game,
+            "crane\n zzzzz \nslate\nplate\n".as_bytes(),
+            Game::is_over,
+            |_| {},
+            |game, line| game.play(line, &dict, &cache).map(|_| ()),
+            |e| errors.push(e.to_string()),
+        )
+        .unwrap();
+        assert!(finished);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(game.num_guess(), 2);
+        assert!(game.is_won());
+    }
+
+    #[test]
+    fn play_lines_tells_when_the_input_ends_first() {
+        let dict = dict();
+        let cache = PatternTable::new();
+        let mut game = Game::new("slate", &dict, 6, false).unwrap();
+        let finished = play_lines(
+            &mut game,
+            "crane\n".as_bytes(),
+            Game::is_over,
+            |_| {},
+            |game, line| game.play(line, &dict, &cache).map(|_| ()),
+            |_| {},
+        )
+        .unwrap();
+        assert!(!finished);
+        assert_eq!(game.num_guess(), 1);
+    }
+
+    #[test]
+    fn read_plays_takes_feedback_given_or_from_the_answer() {
+        let solver = Solver::new(
+            dict(),
+            vec![1.0; 5],
+            PatternTable::new(),
+            temp_store(),
+            SearchOptions::default(),
+        );
+        let entries = ["CRANE".to_string(), "slate:ggggg".to_string()];
+        let plays = read_plays(&solver, Some("Slate"), &entries, 6).unwrap();
+        assert_eq!(plays[0].0, "crane");
+        assert_eq!(plays[0].1, FeedBack::parse("bbgbg").unwrap());
+        // The feedback given has to match the answer
+        let wrong = ["crane:bbbbb".to_string()];
+        assert!(read_plays(&solver, Some("slate"), &wrong, 6).is_err());
+        assert!(read_plays(&solver, None, &wrong, 6).is_ok());
+        assert!(read_plays(&solver, None, &["crane".to_string()], 6).is_err());
+    }
+
+    #[test]
+    fn answers_are_drawn_once_and_never_without_weight() {
+        let dict = dict();
+        let weights = [1.0, 0.0, 1.0, 1.0, 1.0];
+        for _ in 0..20 {
+            let mut answers = draw_answers(&dict, &weights, 4).unwrap();
+            assert!(!answers.contains(&"crate".to_string()));
+            answers.dedup();
+            assert_eq!(answers.len(), 4);
+        }
+    }
+}
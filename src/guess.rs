@@ -1,11 +1,20 @@
-use std::collections::HashMap;
+use crate::constraints::Constraints;
+use crate::error::{Error, Result};
+use crate::share;
+use rand::{seq::SliceRandom, Rng};
+use std::{collections::HashMap, fmt};
 
-#[derive(Clone, Hash, Eq, PartialEq)]
+/// The set of dictionary words still consistent with the feedback received so far.
+///
+/// One bit per dictionary word, as every node of a search tree holds one.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct Guess {
-    possible_words: Vec<bool>,
+    bits: Vec<u64>,
+    len: usize,
 }
 
-#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+/// Colour of one tile of a played word.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum FeedBack {
     Black,
     Yellow,
@@ -13,6 +22,35 @@ pub enum FeedBack {
 }
 
 impl FeedBack {
+    /// Parse one tile of a pattern such as `bgybb`, or of a share grid such as `⬛🟩🟨⬛⬛`.
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'g' => Some(FeedBack::Green),
+            'y' => Some(FeedBack::Yellow),
+            'b' => Some(FeedBack::Black),
+            c => share::tile(c),
+        }
+    }
+    pub fn to_char(self) -> char {
+        match self {
+            FeedBack::Green => 'g',
+            FeedBack::Yellow => 'y',
+            FeedBack::Black => 'b',
+        }
+    }
+    /// Parse a whole pattern such as `bgybb` or `⬛🟩🟨⬛⬛`.
+    pub fn parse(pattern: &str) -> Result<Vec<Self>> {
+        pattern
+            .chars()
+            .filter(|&c| c != '\u{fe0f}')
+            .map(|c| {
+                Self::from_char(c).ok_or_else(|| {
+                    Error::Parse(format!("invalid feedback {:?} in {:?}", c, pattern))
+                })
+            })
+            .collect()
+    }
+    /// Feedback of playing `word` when the answer is `solution`, looked up in `cache` first.
     pub fn evaluate(
         word: &str,
         solution: &str,
@@ -21,12 +59,17 @@ impl FeedBack {
         if let Some(feedback) = cache.get(&(word.to_string(), solution.to_string())) {
             return feedback.clone();
         }
+        Self::compute(word, solution)
+    }
+    /// Feedback of playing `word` when the answer is `solution`, without a pattern table.
+    pub fn compute(word: &str, solution: &str) -> Vec<FeedBack> {
+        let solution: Vec<char> = solution.chars().collect();
         let feedback: Vec<FeedBack> = word
             .chars()
             .enumerate()
             .map(|(i, c)| {
-                if solution.contains(c) {
-                    if solution.chars().nth(i) == Some(c) {
+                if solution.contains(&c) {
+                    if solution.get(i) == Some(&c) {
                         FeedBack::Green
                     } else {
                         FeedBack::Yellow
@@ -40,69 +83,283 @@ impl FeedBack {
     }
 }
 
+impl fmt::Display for Guess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for i in 0..self.len {
+            f.write_str(if self.has(i) { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
 impl Guess {
+    /// Every word of `dict` is a candidate.
     pub fn new(dict: &[String]) -> Self {
-        let possible_words = vec![true; dict.len()];
-        Self { possible_words }
+        let mut guess = Self::empty(dict.len());
+        (0..dict.len()).for_each(|i| guess.set(i));
+        guess
     }
-    pub fn from_string(possible_words: &str) -> Self {
-        let possible_words = possible_words
-            .chars()
-            .map(|c| c == '1')
-            .collect::<Vec<bool>>();
-        Self { possible_words }
+    /// No word of a dictionary of `len` words is a candidate.
+    fn empty(len: usize) -> Self {
+        Self {
+            bits: vec![0; len.div_ceil(64)],
+            len,
+        }
     }
-    pub fn to_string(&self) -> String {
-        self.possible_words
-            .iter()
-            .map(|&b| if b { "1".to_string() } else { "0".to_string() })
-            .collect::<Vec<String>>()
-            .join("")
+    fn has(&self, i: usize) -> bool {
+        self.bits[i / 64] & (1 << (i % 64)) != 0
     }
-    pub fn solutions(&self, dict: &[String]) -> Vec<String> {
-        self.possible_words
+    fn set(&mut self, i: usize) {
+        self.bits[i / 64] |= 1 << (i % 64);
+    }
+    /// Indices in the dictionary of the candidates.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|&i| self.has(i))
+    }
+    /// Pack the candidate set into bytes, one bit per dictionary word.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bits
             .iter()
-            .enumerate()
-            .filter(|(_, &b)| b)
-            .map(|(i, _)| dict[i].clone())
+            .flat_map(|word| word.to_le_bytes())
+            .take(self.len.div_ceil(8))
             .collect()
     }
+    pub fn from_bytes(bytes: &[u8], dict_len: usize) -> Self {
+        let mut guess = Self::empty(dict_len);
+        for i in 0..dict_len {
+            if bytes
+                .get(i / 8)
+                .is_some_and(|byte| byte & (1 << (i % 8)) != 0)
+            {
+                guess.set(i);
+            }
+        }
+        guess
+    }
+    /// The candidate words.
+    pub fn solutions(&self, dict: &[String]) -> Vec<String> {
+        self.indices().map(|i| dict[i].clone()).collect()
+    }
     pub fn num_solutions(&self) -> usize {
-        self.possible_words.iter().filter(|&&b| b).count()
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+    /// Whether every candidate of `self` is a candidate of `other`.
+    pub fn is_subset(&self, other: &Guess) -> bool {
+        self.bits
+            .iter()
+            .zip(&other.bits)
+            .all(|(bits, other)| bits & !other == 0)
+    }
+    /// Whether `word` is one of the candidates.
+    pub fn contains(&self, word: &str, dict: &[String]) -> bool {
+        dict.iter()
+            .position(|w| w == word)
+            .is_some_and(|i| self.has(i))
+    }
+    /// Number of candidates giving each feedback when `word` is played.
+    pub fn feedback_counts(
+        &self,
+        word: &str,
+        dict: &[String],
+        cache: &HashMap<(String, String), Vec<FeedBack>>,
+    ) -> HashMap<Vec<FeedBack>, usize> {
+        let mut counts = HashMap::new();
+        for solution in self.solutions(dict) {
+            *counts
+                .entry(FeedBack::evaluate(word, &solution, cache))
+                .or_insert(0) += 1;
+        }
+        counts
+    }
+    /// Split the candidates by the feedback they give when `word` is played.
+    pub fn partition(
+        &self,
+        word: &str,
+        dict: &[String],
+        cache: &HashMap<(String, String), Vec<FeedBack>>,
+    ) -> HashMap<Vec<FeedBack>, Guess> {
+        let mut buckets: HashMap<Vec<FeedBack>, Guess> = HashMap::new();
+        for i in self.indices() {
+            buckets
+                .entry(FeedBack::evaluate(word, &dict[i], cache))
+                .or_insert_with(|| Self::empty(dict.len()))
+                .set(i);
+        }
+        buckets
+    }
+    /// Total prior weight of the candidates.
+    pub fn weight(&self, weights: &[f32]) -> f32 {
+        self.indices().map(|i| weights[i]).sum()
+    }
+    /// Pick a candidate at random, each with a probability proportional to its weight.
+    pub fn choose_weighted<R: Rng>(
+        &self,
+        dict: &[String],
+        weights: &[f32],
+        rng: &mut R,
+    ) -> Option<String> {
+        let candidates: Vec<usize> = self.indices().collect();
+        candidates
+            .choose_weighted(rng, |&i| weights[i])
+            .ok()
+            .map(|&i| dict[i].clone())
+    }
+    /// Number and total weight of the candidates giving each feedback when `word` is played.
+    pub fn feedback_weights(
+        &self,
+        word: &str,
+        dict: &[String],
+        weights: &[f32],
+        cache: &HashMap<(String, String), Vec<FeedBack>>,
+    ) -> HashMap<Vec<FeedBack>, (usize, f32)> {
+        let mut buckets = HashMap::new();
+        for i in self.indices() {
+            let bucket = buckets
+                .entry(FeedBack::evaluate(word, &dict[i], cache))
+                .or_insert((0, 0.0));
+            bucket.0 += 1;
+            bucket.1 += weights[i];
+        }
+        buckets
+    }
+    /// Information, in bits, gained on average by playing `word`.
+    pub fn entropy(
+        &self,
+        word: &str,
+        dict: &[String],
+        weights: &[f32],
+        cache: &HashMap<(String, String), Vec<FeedBack>>,
+    ) -> f32 {
+        let total = self.weight(weights);
+        self.feedback_weights(word, dict, weights, cache)
+            .values()
+            .filter(|(_, weight)| *weight > 0.0)
+            .map(|(_, weight)| {
+                let p = weight / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+    /// Expected number of candidates left after playing `word`.
+    pub fn expected_remaining(
+        &self,
+        word: &str,
+        dict: &[String],
+        weights: &[f32],
+        cache: &HashMap<(String, String), Vec<FeedBack>>,
+    ) -> f32 {
+        let total = self.weight(weights);
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.feedback_weights(word, dict, weights, cache)
+            .values()
+            .map(|&(count, weight)| count as f32 * weight)
+            .sum::<f32>()
+            / total
     }
-    pub fn refine(&self, word: &str, feedback: &[FeedBack], dict: &[String]) -> Self {
+    /// Keep the candidates consistent with `feedback` for `word`, letter
+    /// counts included.
+    ///
+    /// The search, the solver and the multi-board games all narrow their
+    /// candidates here, so a position reached by playing is the same set the
+    /// search tree and the store key it by.
+    pub fn refine(&self, word: &str, feedback: &[FeedBack], dict: &[String]) -> Result<Self> {
         if feedback.iter().all(|fb| *fb == FeedBack::Green) {
-            let mut possible_words = vec![false; dict.len()];
-            possible_words[dict
+            let mut solved = Self::empty(dict.len());
+            solved.set(
+                dict.iter().position(|w| w == word).ok_or_else(|| {
+                    Error::Dictionary(format!("word {:?} not found in dict", word))
+                })?,
+            );
+            return Ok(solved);
+        }
+        let mut constraints = Constraints::new(feedback.len());
+        constraints.add(word, feedback)?;
+        let word: Vec<char> = word.chars().collect();
+        let mut refined = Self::empty(dict.len());
+        for i in self.indices() {
+            let w: Vec<char> = dict[i].chars().collect();
+            let consistent = feedback
                 .iter()
-                .position(|w| w == word)
-                .expect("word not found in dict")] = true;
-            return Self { possible_words };
+                .enumerate()
+                .fold(true, |acc, (j, &feedback)| {
+                    acc && match feedback {
+                        FeedBack::Black => w.get(j) != word.get(j),
+                        FeedBack::Green => w.get(j) == word.get(j),
+                        FeedBack::Yellow => w
+                            .iter()
+                            .enumerate()
+                            .any(|(k, c)| k != j && Some(c) == word.get(j)),
+                    }
+                });
+            if consistent && constraints.allows(&dict[i]) {
+                refined.set(i);
+            }
         }
-        let new_possible_words = self
-            .possible_words
-            .iter()
-            .enumerate()
-            .map(|(i, &possible)| {
-                let w = &dict[i];
-                possible
-                    && feedback
-                        .iter()
-                        .enumerate()
-                        .fold(true, |acc, (j, &feedback)| {
-                            acc && match feedback {
-                                FeedBack::Black => w.chars().nth(j) != word.chars().nth(j),
-                                FeedBack::Green => w.chars().nth(j) == word.chars().nth(j),
-                                FeedBack::Yellow => w
-                                    .chars()
-                                    .enumerate()
-                                    .any(|(k, c)| k != j && Some(c) == word.chars().nth(j)),
-                            }
-                        })
-            })
-            .collect();
-        Self {
-            possible_words: new_possible_words,
+        Ok(refined)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    fn dict() -> Vec<String> {
+        ["crane", "crate", "slate", "pious"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn words_without_weight_are_never_drawn() {
+        let dict = dict();
+        let weights = [0.0, 1.0, 0.0, 0.0];
+        let guess = Guess::new(&dict);
+        for _ in 0..20 {
+            let word = guess.choose_weighted(&dict, &weights, &mut thread_rng());
+            assert_eq!(word.as_deref(), Some("crate"));
         }
+        assert_eq!(
+            guess.choose_weighted(&dict, &[0.0; 4], &mut thread_rng()),
+            None
+        );
+    }
+
+    #[test]
+    fn likely_answers_weigh_on_entropy_and_remaining() {
+        let dict = dict();
+        let cache = HashMap::new();
+        let guess = Guess::new(&dict);
+        let uniform = [1.0; 4];
+        // pious splits the words into {pious}, {slate} and {crane, crate}
+        assert_eq!(
+            guess.expected_remaining("pious", &dict, &uniform, &cache),
+            1.5
+        );
+        assert_eq!(guess.entropy("pious", &dict, &uniform, &cache), 1.5);
+        // Almost sure to be pious, the feedback tells next to nothing
+        let skewed = [0.01, 0.01, 0.01, 0.97];
+        assert!(guess.expected_remaining("pious", &dict, &skewed, &cache) < 1.1);
+        assert!(guess.entropy("pious", &dict, &skewed, &cache) < 0.25);
+        assert_eq!(guess.weight(&skewed), 1.0);
+    }
+
+    #[test]
+    fn candidate_sets_pack_into_one_bit_per_word() {
+        let dict = dict();
+        let guess = Guess::new(&dict)
+            .refine("pious", &FeedBack::parse("bbbbb").unwrap(), &dict)
+            .unwrap();
+        // slate is ruled out by its s, as pious has none
+        assert_eq!(guess.to_string(), "1100");
+        assert_eq!(guess.to_bytes(), [0b0011]);
+        assert_eq!(Guess::from_bytes(&guess.to_bytes(), dict.len()), guess);
+        assert!(guess.is_subset(&Guess::new(&dict)));
+        assert!(!Guess::new(&dict).is_subset(&guess));
     }
 }
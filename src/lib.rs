@@ -0,0 +1,45 @@
+//! Play Wordle optimally.
+//!
+//! The crate is organised around a dictionary of words of equal length:
+//!
+//! - [`dictionary`] loads the word list,
+//! - [`guess`] evaluates feedback and narrows down the [`Guess`] candidate set,
+//! - [`constraints`] sums up what the feedback so far says about the answer,
+//! - [`cache`] precomputes the feedback of every word pair,
+//! - [`game`] referees a game against a known answer,
+//! - [`book`] precomputes the first guesses so games skip their costliest searches,
+//! - [`mcts`] searches for the best word with Monte Carlo tree search,
+//! - [`analysis`] grades a game played against the bot's choices,
+//! - [`absurdle`] plays both sides of the adversarial variant,
+//! - [`multi`] plays several boards at once (Dordle, Quordle, ...),
+//! - [`engine`] speaks a line protocol for bot tournaments and referees them,
+//! - [`server`] exposes the solver over HTTP,
+//! - [`progress`] reports long tasks on stderr,
+//! - [`share`] reads and writes the emoji grids Wordle results are shared as,
+//! - [`store`] persists the search statistics between runs,
+//! - [`error`] lists what can go wrong,
+//! - [`solver`] ties the above together behind [`Solver`].
+
+pub mod absurdle;
+pub mod analysis;
+pub mod book;
+pub mod cache;
+pub mod constraints;
+pub mod dictionary;
+pub mod engine;
+pub mod error;
+pub mod game;
+pub mod guess;
+pub mod mcts;
+pub mod multi;
+pub mod progress;
+pub mod server;
+pub mod share;
+pub mod solver;
+pub mod store;
+pub mod word;
+
+pub use error::{Error, Result};
+pub use guess::{FeedBack, Guess};
+pub use mcts::{SearchOptions, SearchResult, Suggestion};
+pub use solver::Solver;
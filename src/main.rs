@@ -1,13 +1,31 @@
-mod guess;
-mod mcts;
-mod word;
+mod output;
+mod tui;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use output::Format;
 use std::{
-    collections::HashMap,
-    fs,
-    io::{self, BufRead, BufReader, Write},
-    path::{Path, PathBuf},
+    io::{self, Read},
+    path::PathBuf,
+    process,
+    time::Duration,
+};
+use wordle_bot::{
+    absurdle::{self, Host},
+    analysis,
+    book::Book,
+    cache::{self, PatternTable},
+    constraints::{Constraints, QueryOrder},
+    dictionary::{self, Dictionary, LoadOptions},
+    engine::{self, Engine, Limits},
+    game::{self, Game},
+    mcts,
+    multi::{self, MultiBoard, Turn},
+    progress::Verbosity,
+    server,
+    share::{self, Theme},
+    store::StateStore,
+    word::{Alphabet, Language},
+    Error, FeedBack, Result, SearchOptions, Solver,
 };
 
 #[derive(Parser, Debug)]
@@ -16,19 +34,37 @@ struct Args {
     #[clap(
         short,
         long,
+        multiple_occurrences = true,
         default_value = "./official.txt",//"/usr/share/dict/words",
-        help = "Path to word list"
+        help = "Path to word list, repeat to merge several lists"
+    )]
+    dict: Vec<PathBuf>,
+    #[clap(
+        short,
+        long,
+        help = "Prior weight of each word being the answer, as word,weight lines"
     )]
-    dict: PathBuf,
+    weights: Option<PathBuf>,
     #[clap(short, long, default_value = "./cache.txt", help = "Solution cache")]
     cache: PathBuf,
     #[clap(
         short,
         long,
-        default_value = "./state",
-        help = "Path to state space folder"
+        default_value = "./state.redb",
+        help = "Path to state store file"
     )]
     state_space: PathBuf,
+    #[clap(
+        long,
+        help = "Keep search statistics in memory only, instead of the state store"
+    )]
+    no_store: bool,
+    #[clap(
+        long,
+        default_value = "./book.json",
+        help = "Opening book, used when it matches the dictionary"
+    )]
+    book: PathBuf,
     #[clap(
         short,
         long,
@@ -36,95 +72,759 @@ struct Args {
         help = "Number of iterations per word"
     )]
     iterations: usize,
-    #[clap(short, long, default_value_t = 4, help = "Number of threads used")]
+    #[clap(long, help = "Stop searching after this many milliseconds")]
+    time_limit: Option<u64>,
+    #[clap(
+        long,
+        default_value_t = 30,
+        help = "Save the search trees every this many seconds, 0 for only at the end"
+    )]
+    checkpoint: u64,
+    #[clap(
+        long,
+        help = "Run only what an interrupted search of the same position left undone"
+    )]
+    resume: bool,
+    #[clap(
+        long,
+        help = "Max search tree nodes kept in memory per thread below the first guess, the rest is spilled to the state store"
+    )]
+    max_nodes: Option<usize>,
+    #[clap(
+        short,
+        long,
+        default_value_t = 4,
+        help = "Number of threads used, 0 for one per core"
+    )]
     thread: usize,
-    #[clap(short, long, default_value_t = 5, help = "Word length")]
+    #[clap(short, long, default_value_t = 5, help = "Word length, in letters")]
     length: u8,
+    #[clap(
+        long,
+        default_value = "en",
+        help = "Dictionary language: en, es, de, no or fr"
+    )]
+    language: Language,
+    #[clap(
+        long,
+        help = "Keep accented letters distinct instead of folding them (é stays é)"
+    )]
+    keep_accents: bool,
+    #[clap(
+        long,
+        help = "Lowercase words with capitals instead of rejecting them as proper nouns"
+    )]
+    fold_case: bool,
     #[clap(short, long, default_value_t = 6, help = "Max number of guesses")]
     max_guess: u8,
-    #[clap(help = "Guesses so far")]
+    #[clap(
+        short = 'k',
+        long,
+        global = true,
+        default_value_t = 5,
+        help = "Number of suggestions shown"
+    )]
+    top: usize,
+    #[clap(
+        short,
+        long,
+        arg_enum,
+        global = true,
+        default_value = "text",
+        help = "Output format"
+    )]
+    format: Format,
+    #[clap(
+        short,
+        long,
+        global = true,
+        conflicts_with = "verbose",
+        help = "Report errors only on stderr, no progress or warnings"
+    )]
+    quiet: bool,
+    #[clap(
+        short,
+        long,
+        global = true,
+        help = "Report every step of long tasks on stderr"
+    )]
+    verbose: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Print share text at the end of self-play games"
+    )]
+    share: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Share with white tiles for absent letters"
+    )]
+    light: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Share with orange and blue tiles instead of green and yellow"
+    )]
+    high_contrast: bool,
+    #[clap(help = "Guesses so far, as word:pattern (e.g. crane:bgybb or crane:⬛🟩🟨⬛⬛)")]
     guesses: Vec<String>,
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
-pub fn generate_cache(dict: &[String]) -> HashMap<(String, String), Vec<guess::FeedBack>> {
-    let mut cache = HashMap::new();
-    for word in dict {
-        for solution in dict {
-            guess::FeedBack::evaluate(word, solution, &mut cache);
-        }
-    }
-    cache
-}
-
-pub fn export_cache(cache: &HashMap<(String, String), Vec<guess::FeedBack>>, out: &Path) {
-    let mut file = fs::File::create(out).unwrap();
-    for (key, value) in cache {
-        let mut line = "".to_string() + &key.0 + "," + &key.1 + ",";
-        for fb in value {
-            line += match fb {
-                guess::FeedBack::Green => "g",
-                guess::FeedBack::Yellow => "y",
-                guess::FeedBack::Black => "b",
-            };
-        }
-        line += "\n";
-        file.write_all(line.as_bytes()).unwrap();
-    }
-}
-
-pub fn import_cache(
-    inp: &Path,
-) -> Result<HashMap<(String, String), Vec<guess::FeedBack>>, io::Error> {
-    let file = BufReader::new(fs::File::open(inp)?);
-    let mut cache = HashMap::new();
-    for line in file.lines() {
-        let line = line?;
-        let mut iter = line.split(',');
-        let word = iter.next().unwrap();
-        let solution = iter.next().unwrap();
-        let mut feedback = Vec::new();
-        for fb in iter.next().unwrap().chars() {
-            match fb {
-                'g' => feedback.push(guess::FeedBack::Green),
-                'y' => feedback.push(guess::FeedBack::Yellow),
-                'b' => feedback.push(guess::FeedBack::Black),
-                _ => panic!("Invalid feedback"),
-            }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the solution cache
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+    /// Report how the word lists were loaded and why words were rejected
+    Dictionary,
+    /// Read share text (Wordle 1,234 4/6 and its grid) from stdin and print its patterns
+    Share,
+    /// List the words matching a pattern and letter hints, without searching
+    Query(QueryArgs),
+    /// Speak the engine protocol on stdin and stdout, for bot tournaments
+    Engine,
+    /// Run engines through the answer list and compare how they did
+    Referee {
+        #[clap(
+            long = "engine",
+            multiple_occurrences = true,
+            required = true,
+            help = "Command starting an engine, repeat for each engine"
+        )]
+        engines: Vec<String>,
+        #[clap(long, help = "Number of answers played, all of them by default")]
+        games: Option<usize>,
+    },
+    /// Precompute the opening book
+    Book {
+        #[clap(
+            long,
+            default_value_t = 2,
+            help = "Number of guesses covered: 1 for the first guess, 2 for the replies too, ..."
+        )]
+        depth: u8,
+    },
+    /// Solve interactively in a full-screen terminal UI
+    Tui,
+    /// Grade the guesses of a game against the words the bot would have played
+    Analyze {
+        #[clap(
+            long,
+            help = "Answer of the game, to give words without their feedback"
+        )]
+        answer: Option<String>,
+        #[clap(
+            required = true,
+            help = "Words played, as word:pattern, or as word with --answer"
+        )]
+        plays: Vec<String>,
+    },
+    /// Answer requests from other programs over HTTP
+    Serve {
+        #[clap(long, default_value = "127.0.0.1:8080", help = "Address to listen on")]
+        address: String,
+    },
+    /// Play a game yourself, reading words from stdin
+    Play {
+        #[clap(long, help = "Hints found must be used in later guesses")]
+        hard: bool,
+        #[clap(long, help = "Answer to play against instead of a random one")]
+        answer: Option<String>,
+    },
+    /// Play Absurdle, where the host dodges every guess
+    Absurdle {
+        #[clap(long, help = "Play against the host, reading words from stdin")]
+        play: bool,
+        #[clap(long, help = "Let the solver play against the host")]
+        self_play: bool,
+        #[clap(help = "Words played so far, the host's answers are replayed")]
+        guesses: Vec<String>,
+    },
+    /// Solve several boards at once (Dordle, Quordle, Octordle, Sedecordle)
+    Multi {
+        #[clap(short, long, default_value_t = 4, help = "Number of boards")]
+        boards: usize,
+        #[clap(long, help = "Read each guess and its feedback from stdin")]
+        interactive: bool,
+        #[clap(long, help = "Play against random answers")]
+        self_play: bool,
+        #[clap(long, default_value_t = 1, help = "Number of self-play games")]
+        games: usize,
+        #[clap(help = "Guesses so far, as word:pattern,... with - for solved boards")]
+        guesses: Vec<String>,
+    },
+}
+
+impl Command {
+    /// Whether the command prints a line per turn or game as it goes,
+    /// rather than one result at the end.
+    fn streams(&self) -> bool {
+        match self {
+            Command::Play { .. } | Command::Referee { .. } => true,
+            Command::Absurdle {
+                play,
+                self_play,
+                guesses,
+            } => *play || *self_play || !guesses.is_empty(),
+            Command::Multi {
+                interactive,
+                self_play,
+                ..
+            } => *interactive || *self_play,
+            _ => false,
         }
-        cache.insert((word.to_string(), solution.to_string()), feedback);
     }
-    Ok(cache)
+}
+
+#[derive(clap::Args, Debug)]
+struct QueryArgs {
+    #[clap(help = "Letter at each position, ? where unknown, such as ?r??e")]
+    pattern: String,
+    #[clap(
+        long,
+        default_value = "",
+        help = "Letters the word has, a letter given twice appearing twice"
+    )]
+    has: String,
+    #[clap(long, default_value = "", help = "Letters the word does not have")]
+    without: String,
+    #[clap(
+        long = "not",
+        multiple_occurrences = true,
+        help = "Letters not at a position, as position:letters such as 1:a"
+    )]
+    not_at: Vec<String>,
+    #[clap(
+        long,
+        default_value = "weight",
+        possible_values = &["weight", "alphabetical"],
+        help = "Order of the words listed"
+    )]
+    sort: QueryOrder,
+    #[clap(long, help = "Show the prior weight of each word")]
+    show_weights: bool,
+    #[clap(long, help = "List at most this many words")]
+    limit: Option<usize>,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Report problems in the solution cache without modifying it
+    Verify,
 }
 
 fn main() {
     let args = Args::parse();
-    let dict: Vec<String> = fs::read_to_string(&args.dict)
-        .expect("Failed to read dictionary")
-        .lines()
-        .filter(|word| word.len() == args.length as usize && word::is_clean(word))
-        .map(|word| word.to_string())
-        .collect();
-    let mut cache;
-    if let Ok(c) = import_cache(&args.cache) {
-        cache = c;
-    } else {
-        cache = generate_cache(&dict);
-        export_cache(&cache, &args.cache);
-    }
-    let guess = guess::Guess::new(&dict);
-    println!(
-        "{}",
-        mcts::search(
-            guess,
-            0,
-            args.max_guess,
+    let format = args.format;
+    if let Err(e) = run(args) {
+        output::print_error(format, &e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    if args.format == Format::Json && args.command.as_ref().is_some_and(Command::streams) {
+        return Err(Error::InvalidInput(
+            "--format json writes a single document, use --format jsonl for a command printing as it goes"
+                .to_string(),
+        ));
+    }
+    let setup = Setup::new(&args);
+    match &args.command {
+        Some(Command::Share) => share_grid(args.format),
+        Some(Command::Dictionary) => {
+            output::print_dictionary_report(args.format, &setup.dictionary()?.report);
+            Ok(())
+        }
+        Some(Command::Query(query_args)) => query(&setup, query_args),
+        Some(Command::Cache {
+            command: CacheCommand::Verify,
+        }) => verify_cache(&setup),
+        Some(Command::Play { hard, answer }) => play(&setup, *hard, answer.as_deref()),
+        Some(Command::Absurdle {
+            play,
+            self_play,
+            guesses,
+        }) => absurdle(&setup, *play, *self_play, guesses),
+        Some(Command::Multi {
+            boards,
+            interactive,
+            self_play,
+            games,
+            guesses,
+        }) => multi(&setup, *boards, *interactive, *self_play, *games, guesses),
+        Some(Command::Referee { engines, games }) => referee(&setup, engines, *games),
+        Some(Command::Book { depth }) => book(&setup, *depth),
+        Some(Command::Engine) => {
+            engine::run(&setup.solver_with_book()?, io::stdin().lock(), io::stdout())
+        }
+        Some(Command::Tui) => tui::run(setup.solver_with_book()?),
+        Some(Command::Analyze { answer, plays }) => analyze(&setup, answer.as_deref(), plays),
+        Some(Command::Serve { address }) => server::serve(address, setup.solver_with_book()?),
+        None => search(&setup),
+    }
+}
+
+/// What the subcommands share, each loading only as much of it as it needs.
+struct Setup<'a> {
+    args: &'a Args,
+    verbosity: Verbosity,
+    load_options: LoadOptions,
+}
+
+impl<'a> Setup<'a> {
+    fn new(args: &'a Args) -> Self {
+        let verbosity = match (args.quiet, args.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (_, true) => Verbosity::Verbose,
+            _ => Verbosity::Normal,
+        };
+        let load_options = LoadOptions {
+            length: args.length as usize,
+            alphabet: Alphabet::for_language(args.language),
+            fold_accents: !args.keep_accents,
+            fold_case: args.fold_case,
+        };
+        Self {
+            args,
+            verbosity,
+            load_options,
+        }
+    }
+    /// `word` as typed by the user, normalized the way the dictionary is.
+    fn normalize(&self, word: &str) -> String {
+        self.load_options
+            .alphabet
+            .normalize_input(word, self.load_options.fold_accents)
+    }
+    fn theme(&self) -> Theme {
+        Theme {
+            light: self.args.light,
+            high_contrast: self.args.high_contrast,
+        }
+    }
+    fn dictionary(&self) -> Result<Dictionary> {
+        dictionary::load(&self.args.dict, &self.load_options)
+    }
+    /// The words of the dictionary and the prior weight of each being the answer.
+    fn words(&self) -> Result<(Vec<String>, Vec<f32>)> {
+        let dictionary = self.dictionary()?;
+        if self.verbosity == Verbosity::Verbose
+            || (self.verbosity == Verbosity::Normal && !dictionary.report.rejected.is_empty())
+        {
+            eprintln!("{}", dictionary.report);
+        }
+        let dict = dictionary.words;
+        let weights = match (&self.args.weights, dictionary.weights) {
+            (Some(path), _) => dictionary::load_weights(path, &dict, &self.load_options)?,
+            (None, Some(weights)) => weights,
+            (None, None) => dictionary::uniform_weights(&dict),
+        };
+        Ok((dict, weights))
+    }
+    /// The words, their weights and the pattern table of their feedback.
+    fn table(&self) -> Result<(Vec<String>, Vec<f32>, PatternTable)> {
+        let (dict, weights) = self.words()?;
+        let threads = cache::worker_count(self.args.thread);
+        let cache = cache::load_table(&self.args.cache, &dict, threads, self.verbosity)?;
+        Ok((dict, weights, cache))
+    }
+    /// A solver searching with the options given on the command line.
+    fn solver(&self) -> Result<Solver> {
+        let args = self.args;
+        let (dict, weights, cache) = self.table()?;
+        let store = if args.no_store {
+            StateStore::in_memory()?
+        } else {
+            open_store(args, self.verbosity)?
+        };
+        let mut solver = Solver::new(
+            dict,
+            weights,
+            cache,
+            store,
+            SearchOptions {
+                max_guess: args.max_guess,
+                iterations: args.iterations,
+                threads: cache::worker_count(args.thread),
+                top: args.top,
+                time_limit: args.time_limit.map(Duration::from_millis),
+                verbosity: self.verbosity,
+                checkpoint: (args.checkpoint > 0).then(|| Duration::from_secs(args.checkpoint)),
+                resume: args.resume,
+                max_nodes: args.max_nodes,
+                cancel: None,
+            },
+        );
+        solver.set_alphabet(
+            self.load_options.alphabet.clone(),
+            self.load_options.fold_accents,
+        );
+        Ok(solver)
+    }
+    /// Like [`Setup::solver`], answering from the opening book when it matches.
+    fn solver_with_book(&self) -> Result<Solver> {
+        let mut solver = self.solver()?;
+        let path = &self.args.book;
+        if path.exists() {
+            // A stale book is no reason to stop, the search can do without
+            match Book::load(path, &solver) {
+                Ok(book) => {
+                    if self.verbosity == Verbosity::Verbose {
+                        eprintln!("{}: {} positions", path.display(), book.entries.len());
+                    }
+                    solver.use_book(&book)?
+                }
+                Err(e) if self.verbosity > Verbosity::Quiet => {
+                    eprintln!("{}, searching without it", e)
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(solver)
+    }
+}
+
+fn share_grid(format: Format) -> Result<()> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    output::print_share_grid(format, &share::parse(&text)?);
+    Ok(())
+}
+
+fn query(setup: &Setup, query: &QueryArgs) -> Result<()> {
+    let (dict, weights) = setup.words()?;
+    let constraints = Constraints::query(
+        &query.pattern,
+        &query.has,
+        &query.without,
+        &query.not_at,
+        &setup.load_options.alphabet,
+        setup.load_options.fold_accents,
+    )?;
+    if constraints.fixed.len() != setup.args.length as usize {
+        return Err(Error::InvalidInput(format!(
+            "{:?} has {} letters, the words {}",
+            query.pattern,
+            constraints.fixed.len(),
+            setup.args.length
+        )));
+    }
+    let (total, matches) = constraints.matches(&dict, &weights, query.sort, query.limit);
+    output::print_query(
+        setup.args.format,
+        &constraints,
+        total,
+        &matches,
+        query.show_weights,
+    );
+    Ok(())
+}
+
+fn verify_cache(setup: &Setup) -> Result<()> {
+    let args = setup.args;
+    let (dict, _) = setup.words()?;
+    let problems = cache::verify_cache(&args.cache, &dict)?;
+    if problems.is_empty() {
+        output::print_cache_problems(args.format, &args.cache, &problems);
+        return Ok(());
+    }
+    // Reported like any other failure, so the exit code and JSON document match
+    let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+    Err(Error::Parse(format!(
+        "{}: {}",
+        args.cache.display(),
+        problems.join("; ")
+    )))
+}
+
+fn play(setup: &Setup, hard: bool, answer: Option<&str>) -> Result<()> {
+    let args = setup.args;
+    let (dict, weights, cache) = setup.table()?;
+    let secret = match answer {
+        Some(answer) => setup.normalize(answer),
+        None => game::draw_answers(&dict, &weights, 1)?.remove(0),
+    };
+    let mut game = Game::new(&secret, &dict, args.max_guess, hard)?;
+    let finished = game::play_lines(
+        &mut game,
+        io::stdin().lock(),
+        Game::is_over,
+        |_| eprint!("word> "),
+        |game, line| {
+            game.play(&setup.normalize(line), &dict, &cache)?;
+            output::print_play(args.format, game);
+            Ok(())
+        },
+        |e| output::print_error(args.format, e),
+    )?;
+    if finished {
+        output::print_game(args.format, &game);
+        if args.share {
+            let text = share::generate(
+                "Wordle",
+                None,
+                &game.rows(),
+                Some(game.max_guess()),
+                game.hard_mode(),
+                setup.theme(),
+            );
+            output::print_share(args.format, &text);
+        }
+    }
+    Ok(())
+}
+
+fn absurdle(setup: &Setup, play: bool, self_play: bool, guesses: &[String]) -> Result<()> {
+    let format = setup.args.format;
+    let (dict, _, cache) = setup.table()?;
+    let mut host = Host::new(&dict);
+    let mut rows = Vec::new();
+    for word in guesses {
+        let word = setup.normalize(word);
+        let feedback = host.respond(&word, &dict, &cache)?;
+        output::print_absurdle_turn(format, &host, &word, &feedback);
+        rows.push(feedback);
+    }
+    if self_play {
+        absurdle::self_play(&mut host, &dict, &cache, |host, word, feedback| {
+            output::print_absurdle_turn(format, host, word, feedback);
+            rows.push(feedback.to_vec());
+        })?;
+        if setup.args.share {
+            let text = share::generate("Absurdle", None, &rows, None, false, setup.theme());
+            output::print_share(format, &text);
+        }
+    } else if play {
+        game::play_lines(
+            &mut host,
+            io::stdin().lock(),
+            Host::is_solved,
+            |_| eprint!("word> "),
+            |host, line| {
+                let word = setup.normalize(line);
+                let feedback = host.respond(&word, &dict, &cache)?;
+                output::print_absurdle_turn(format, host, &word, &feedback);
+                Ok(())
+            },
+            |e| output::print_error(format, e),
+        )?;
+    } else if !host.is_solved() {
+        let word = absurdle::minimax_guess(host.candidates(), &dict, &cache)?;
+        output::print_absurdle_suggestion(format, &host, &word);
+    }
+    Ok(())
+}
+
+fn multi(
+    setup: &Setup,
+    boards: usize,
+    interactive: bool,
+    self_play: bool,
+    games: usize,
+    guesses: &[String],
+) -> Result<()> {
+    let args = setup.args;
+    let (dict, weights, cache) = setup.table()?;
+    if self_play {
+        let (won, total_guesses) = multi::self_play_games(
             &dict,
-            args.iterations,
-            &mut cache,
-            &args.state_space,
-            args.thread
-        )
+            &weights,
+            &cache,
+            boards,
+            games,
+            |i, game, turn| output::print_multi_turn(args.format, i, game, turn),
+            |i, secrets, game, turns| {
+                output::print_multi_game(args.format, i, secrets, game);
+                // Share grids only exist for a single board
+                if let (true, 1) = (args.share, boards) {
+                    let rows: Vec<Vec<FeedBack>> = turns
+                        .iter()
+                        .filter_map(|turn| turn.feedbacks[0].clone())
+                        .collect();
+                    let text = share::generate(
+                        "Wordle",
+                        None,
+                        &rows,
+                        Some(game.max_guess()),
+                        false,
+                        setup.theme(),
+                    );
+                    output::print_share(args.format, &text);
+                }
+            },
+        )?;
+        output::print_self_play_summary(args.format, games, won, total_guesses);
+        return Ok(());
+    }
+    let mut game = MultiBoard::new(&dict, boards)?;
+    for entry in guesses {
+        let turn = Turn::parse(entry)?;
+        game.play(&setup.normalize(&turn.word), &turn.feedbacks, &dict)?;
+    }
+    if interactive {
+        let finished = game::play_lines(
+            &mut game,
+            io::stdin().lock(),
+            MultiBoard::is_over,
+            |game| {
+                let suggestions = game.suggest(&dict, &weights, &cache, args.top);
+                output::print_multi(args.format, game, &suggestions);
+                eprint!("word:pattern,...> ");
+            },
+            |game, line| {
+                let turn = Turn::parse(line)?;
+                game.play(&setup.normalize(&turn.word), &turn.feedbacks, &dict)
+            },
+            |e| output::print_error(args.format, e),
+        )?;
+        if finished {
+            output::print_multi(args.format, &game, &[]);
+        }
+        return Ok(());
+    }
+    let suggestions = game.suggest(&dict, &weights, &cache, args.top);
+    output::print_multi(args.format, &game, &suggestions);
+    Ok(())
+}
+
+fn referee(setup: &Setup, engines: &[String], games: Option<usize>) -> Result<()> {
+    let args = setup.args;
+    let (dict, _, cache) = setup.table()?;
+    let mut engines = engines
+        .iter()
+        .map(|command| Engine::start(command))
+        .collect::<Result<Vec<Engine>>>()?;
+    let answers = &dict[..games.unwrap_or(dict.len()).min(dict.len())];
+    let limits = Limits {
+        iterations: Some(args.iterations),
+        movetime: args.time_limit.map(Duration::from_millis),
+    };
+    let standings = engine::referee(
+        &mut engines,
+        answers,
+        &dict,
+        &cache,
+        args.max_guess,
+        &limits,
+        |answer, games| output::print_referee_game(args.format, answer, games),
+    )?;
+    output::print_referee_summary(args.format, &standings);
+    Ok(())
+}
+
+fn book(setup: &Setup, depth: u8) -> Result<()> {
+    // Searched afresh, not answered from the book being replaced
+    let solver = setup.solver()?;
+    stop_searches_on_signal(setup.verbosity)?;
+    let verbosity = setup.verbosity;
+    let book = Book::generate(&solver, depth, |done, history| {
+        if verbosity > Verbosity::Quiet {
+            eprintln!("book: {} positions done, searching {:?}", done, history)
+        }
+    })?;
+    book.save(&setup.args.book)?;
+    output::print_book(setup.args.format, &setup.args.book, &book);
+    Ok(())
+}
+
+fn analyze(setup: &Setup, answer: Option<&str>, plays: &[String]) -> Result<()> {
+    let solver = setup.solver_with_book()?;
+    stop_searches_on_signal(setup.verbosity)?;
+    let plays = game::read_plays(&solver, answer, plays, setup.args.max_guess)?;
+    output::print_analysis(setup.args.format, &analysis::analyze(&solver, &plays)?);
+    Ok(())
+}
+
+/// Suggest the next guesses after those given on the command line.
+fn search(setup: &Setup) -> Result<()> {
+    let args = setup.args;
+    let solver = setup.solver_with_book()?;
+    stop_searches_on_signal(setup.verbosity)?;
+    let guess = solver.replay(&args.guesses)?;
+    let plays = game::read_plays(&solver, None, &args.guesses, args.max_guess)?;
+    let constraints = if plays.is_empty() {
+        None
+    } else {
+        Some(Constraints::from_history(args.length as usize, &plays)?)
+    };
+    output::print_result(
+        args.format,
+        &solver.search(&guess, args.guesses.len() as u8)?,
+        constraints.as_ref(),
     );
-    //let mut guess = guess::Guess::new(args.length);
-    //println!("Hello {:?}!", args.dict)
+    Ok(())
+}
+
+/// The state store, or one in memory for an engine whose store another engine holds.
+fn open_store(args: &Args, verbosity: Verbosity) -> Result<StateStore> {
+    match StateStore::open(&args.state_space) {
+        // Engines started side by side by a referee would lock each other out
+        Err(Error::Store(redb::Error::DatabaseAlreadyOpen))
+            if matches!(args.command, Some(Command::Engine)) =>
+        {
+            if verbosity > Verbosity::Quiet {
+                eprintln!(
+                    "{} is in use, keeping search statistics in memory",
+                    args.state_space.display()
+                );
+            }
+            StateStore::in_memory()
+        }
+        store => store,
+    }
+}
+
+/// On SIGINT or SIGTERM, have running searches save their trees and stop.
+/// A second signal quits at once.
+///
+/// Only for the modes running one search and exiting: long-lived modes answer
+/// Ctrl-C by quitting.
+fn stop_searches_on_signal(verbosity: Verbosity) -> Result<()> {
+    ctrlc::set_handler(move || {
+        if mcts::is_interrupted() {
+            process::exit(130);
+        }
+        if verbosity > Verbosity::Quiet {
+            eprintln!("\ninterrupted, saving the search, interrupt again to quit now");
+        }
+        mcts::interrupt();
+    })
+    .map_err(|e| Error::Io(io::Error::other(format!("cannot handle signals: {}", e))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Args {
+        Args::try_parse_from([&["wordle-bot"], args].concat()).unwrap()
+    }
+
+    #[test]
+    fn json_is_rejected_for_commands_printing_as_they_go() {
+        for command in [
+            &["play"][..],
+            &["multi", "--self-play"],
+            &["absurdle", "crane"],
+        ] {
+            let result = run(args(&[&["--format", "json"], command].concat()));
+            assert!(
+                matches!(result, Err(Error::InvalidInput(_))),
+                "{:?}",
+                command
+            );
+        }
+        assert!(!Command::Absurdle {
+            play: false,
+            self_play: false,
+            guesses: Vec::new(),
+        }
+        .streams());
+    }
 }
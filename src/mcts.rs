@@ -1,25 +1,55 @@
+use crate::cache::PatternTable;
+use crate::error::{Error, Result};
 use crate::guess::{FeedBack, Guess};
+use crate::progress::{Progress, Verbosity};
+use crate::store::{Run, StateStore};
 use rand::{seq::SliceRandom, thread_rng};
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    fs,
-    io::{self, BufRead, Write},
-    path::Path,
-    process,
-    sync::Arc,
+    collections::{hash_map::Entry, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
 const EXPLORATION_FACTOR: f32 = std::f32::consts::SQRT_2;
 
-type StateSpace = HashMap<(Guess, String), Node>;
+/// Set once a stop was requested, checked between iterations by every search thread.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
+/// Ask running searches to save their trees and stop, as on Ctrl-C.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Whether a search should stop, interrupted or no longer wanted.
+fn is_stopped(cancel: Option<&AtomicBool>) -> bool {
+    is_interrupted() || cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+}
+
+/// Search tree of one solution, keyed by the parent candidate set and the word played.
+///
+/// The root is keyed by its candidate set and an empty word.
+pub type StateSpace = HashMap<(Guess, String), Node>;
+
+/// Statistics of one node of the search tree.
 #[derive(Clone)]
-struct Node {
+pub struct Node {
     pub guess: Guess,
     pub cumulative_score: f32,
     pub num_simulations: u32,
+    /// Number of simulations through the node that found the solution
+    pub num_wins: u32,
     pub num_guess: u8,
+    /// Whether the node changed since it was loaded from the store
+    pub dirty: bool,
 }
 
 impl Node {
@@ -33,178 +63,296 @@ impl Node {
     }
 }
 
+/// Options controlling the Monte Carlo tree search.
+#[derive(Clone, Debug)]
+pub struct SearchOptions {
+    /// Max number of guesses before the game is lost
+    pub max_guess: u8,
+    /// Number of iterations per solution
+    pub iterations: usize,
+    /// Number of solutions explored in parallel
+    pub threads: usize,
+    /// Number of suggestions to return
+    pub top: usize,
+    /// Stop early once this much time is spent, ranking on the solutions
+    /// explored so far. The first solutions run at least one iteration each.
+    pub time_limit: Option<Duration>,
+    /// How much of the search is reported on stderr
+    pub verbosity: Verbosity,
+    /// Save the trees this often while exploring, so an interruption loses little
+    pub checkpoint: Option<Duration>,
+    /// Run only the iterations an interrupted search of the same position left undone
+    pub resume: bool,
+    /// Max number of nodes each thread keeps in memory below the first guess.
+    /// Beyond it the tree is saved and its least visited nodes are dropped, to
+    /// be read back from the store when the search reaches them again.
+    pub max_nodes: Option<usize>,
+    /// Stop early once set, as when the result is no longer wanted. The trees
+    /// are saved all the same and the search fails as interrupted.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+/// A word worth playing, with the statistics gathered by the search.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub word: String,
+    /// Mean number of guesses left when the game was won, losses counting as 0
+    pub mean_score: f32,
+    /// Number of simulations that played the word
+    pub visits: u32,
+    /// Fraction of those simulations that found the solution
+    pub win_probability: f32,
+    /// Expected number of candidates left after playing the word
+    pub expected_remaining: f32,
+    /// Whether the word can be the solution
+    pub is_candidate: bool,
+}
+
+/// Outcome of a search, the best suggestions first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// Number of candidates the search started from
+    pub candidates: usize,
+    /// Number of simulations run from the starting candidates, across all runs
+    pub simulations: u32,
+    /// Wall-clock time spent searching
+    pub elapsed_ms: u128,
+    pub suggestions: Vec<Suggestion>,
+    /// Whether the result was read from the opening book instead of searched
+    #[serde(default)]
+    pub from_book: bool,
+}
+
+impl SearchResult {
+    /// The best word to play.
+    pub fn best(&self) -> &Suggestion {
+        &self.suggestions[0]
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            max_guess: 6,
+            iterations: 100,
+            threads: 4,
+            top: 5,
+            time_limit: None,
+            verbosity: Verbosity::Normal,
+            checkpoint: Some(Duration::from_secs(30)),
+            resume: false,
+            max_nodes: None,
+            cancel: None,
+        }
+    }
+}
+
+/// Search for the best word to play from `guess`, `num_guess` guesses into the game.
+///
+/// Every possible solution is explored in its own tree, whose statistics are
+/// loaded from and saved back to `store`, before the trees are combined.
+/// Solutions get iterations in proportion to their prior weight in `weights`,
+/// so likely answers dominate the combined statistics.
+///
+/// Trees are saved every [`SearchOptions::checkpoint`] along with the number
+/// of iterations done. After [`interrupt`] the threads save and stop, and the
+/// search fails with [`Error::Interrupted`]; with [`SearchOptions::resume`]
+/// the next search of the same position runs only what was left.
 pub fn search(
     guess: Guess,
     num_guess: u8,
-    max_guess: u8,
     dict: &[String],
-    num_iterations: usize,
-    cache: &HashMap<(String, String), Vec<FeedBack>>,
-    state_space_path: &Path,
-    num_threads: usize,
-) -> String {
-    let arc_dict = Box::new(Arc::new(dict.to_vec()));
-    let arc_cache = Box::new(Arc::new(cache.clone()));
-    let arc_state_space_path = Box::new(Arc::new(state_space_path.to_path_buf()));
-    let mut solutions = guess.solutions(dict);
+    weights: &[f32],
+    cache: &PatternTable,
+    store: &StateStore,
+    options: &SearchOptions,
+) -> Result<SearchResult> {
+    if guess.num_solutions() == 0 {
+        return Err(Error::Search("no candidate word is left".to_string()));
+    }
+    if let [word] = guess.solutions(dict).as_slice() {
+        // Nothing to search once the answer is known, and no child would get simulated
+        return Ok(SearchResult {
+            candidates: 1,
+            simulations: 0,
+            elapsed_ms: 0,
+            from_book: false,
+            suggestions: vec![Suggestion {
+                word: word.clone(),
+                mean_score: options.max_guess.saturating_sub(num_guess + 1) as f32,
+                visits: 0,
+                win_probability: 1.0,
+                expected_remaining: 0.0,
+                is_candidate: true,
+            }],
+        });
+    }
+    let start = Instant::now();
+    let deadline = options.time_limit.map(|limit| start + limit);
+    let max_guess = options.max_guess;
+    let num_threads = options.threads;
+    let mean_weight = guess.weight(weights) / guess.num_solutions() as f32;
+    let mut solutions: Vec<(String, Run)> = dict
+        .iter()
+        .zip(weights)
+        .filter(|(word, _)| guess.contains(word, dict))
+        .map(|(word, &weight)| {
+            let share = weight / mean_weight * options.iterations as f32;
+            let planned = Run::new((share.round() as usize).max(1));
+            let run = if options.resume {
+                store.load_run(word, &guess)?.unwrap_or(planned)
+            } else {
+                planned
+            };
+            Ok((word.clone(), run))
+        })
+        .collect::<Result<_>>()?;
     solutions.shuffle(&mut thread_rng());
-    let state_space: StateSpace = solutions
-        .chunks(num_threads)
-        .map(|x| x.to_vec())
-        .into_iter()
-        .fold(StateSpace::new(), |all_solutions_state_space, solutions| {
-            let handles: Vec<thread::JoinHandle<StateSpace>> = solutions
-                .into_iter()
-                .map(|solution| {
-                    println!("{}", solution);
-                    let local_solution = solution.to_string();
-                    let local_guess = guess.clone();
-                    let local_dict = (*arc_dict).clone();
-                    let local_cache = (*arc_cache).clone();
-                    let local_state_space_path = (*arc_state_space_path).clone();
-                    let mut state = StateSpace::new();
-                    if local_state_space_path
-                        .join(&format!("{}.zst", solution))
-                        .exists()
-                    {
-                        if let Ok(()) =
-                            uncompress_state_space(&local_state_space_path, &local_solution)
-                        {
-                            if let Ok(s) = load_state_space_from_file(
-                                &local_state_space_path
-                                    .join(&Path::new(&format!("{}.csv", local_solution))),
-                            ) {
-                                state = s;
-                            }
+    let mut progress = Progress::new(
+        options.verbosity,
+        "search",
+        (solutions.len(), "solutions"),
+        (
+            solutions.iter().map(|(_, run)| run.remaining()).sum(),
+            "iterations",
+        ),
+    );
+    let checkpoint = options.checkpoint;
+    let max_nodes = options.max_nodes;
+    let cancel = options.cancel.as_deref();
+    // Trees explored since the last checkpoint, only their changed nodes
+    let mut pending: Vec<(String, StateSpace, Run)> = Vec::new();
+    let mut last_checkpoint = Instant::now();
+    let state_space: StateSpace = solutions.chunks(num_threads).map(|x| x.to_vec()).try_fold(
+        StateSpace::new(),
+        |all_solutions_state_space, solutions| {
+            // Past the deadline no new solution is taken, once there is
+            // something to rank
+            let past_deadline = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if is_stopped(cancel) || (past_deadline && !all_solutions_state_space.is_empty()) {
+                return Ok(all_solutions_state_space);
+            }
+            let iterations = solutions.iter().map(|(_, run)| run.remaining()).sum();
+            let num_solutions = solutions.len();
+            let explored: Vec<(String, StateSpace, Run)> = thread::scope(|scope| {
+                let handles: Vec<_> = solutions
+                    .into_iter()
+                    .map(|(solution, run)| {
+                        if options.verbosity == Verbosity::Verbose {
+                            eprintln!("exploring {} for {} iterations", solution, run.remaining());
                         }
-                    }
-                    std::thread::spawn(move || {
-                        let state = explore_one_solution(
-                            state,
-                            local_guess,
-                            local_solution.clone(),
-                            num_guess,
-                            max_guess,
-                            &local_dict,
-                            num_iterations,
-                            &local_cache,
-                        );
-                        save_state_space_to_file(
-                            &state,
-                            &local_state_space_path
-                                .join(Path::new(&format!("{}.csv", local_solution))),
-                        )
-                        .unwrap();
-                        compress_state_space(&local_state_space_path, &local_solution).unwrap();
-                        state
+                        let guess = &guess;
+                        scope.spawn(move || {
+                            let state = match max_nodes {
+                                // The deeper nodes are read as the search reaches them
+                                Some(_) => store.load_children(&solution, guess, dict.len())?,
+                                None => store.load_solution(&solution, guess, dict.len())?,
+                            };
+                            let budget = Budget {
+                                run,
+                                deadline,
+                                checkpoint,
+                                max_nodes,
+                                cancel,
+                                store,
+                            };
+                            let (state, run) = explore_one_solution(
+                                state,
+                                guess.clone(),
+                                solution.clone(),
+                                num_guess,
+                                max_guess,
+                                dict,
+                                weights,
+                                budget,
+                                cache,
+                            )?;
+                            Ok((solution, state, run))
+                        })
                     })
-                })
-                .collect();
-            let mut state_spaces: Vec<StateSpace> = handles
-                .into_iter()
-                .map(|handle| handle.join().unwrap())
-                .collect();
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .map_err(|_| Error::Search("a search thread panicked".to_string()))?
+                    })
+                    .collect::<Result<_>>()
+            })?;
+            let mut state_spaces = Vec::new();
+            for (solution, mut state, run) in explored {
+                // Only the root and its children are combined, the rest goes to the store
+                state_spaces.push(
+                    state
+                        .iter()
+                        .filter(|((parent, _), _)| parent == &guess)
+                        .map(|(key, node)| (key.clone(), node.clone()))
+                        .collect(),
+                );
+                state.retain(|_, node| node.dirty);
+                pending.push((solution, state, run));
+            }
+            if is_stopped(cancel)
+                || checkpoint.is_some_and(|every| last_checkpoint.elapsed() >= every)
+            {
+                save_pending(store, &guess, &mut pending)?;
+                last_checkpoint = Instant::now();
+            }
             state_spaces.push(all_solutions_state_space);
-            combine_state_spaces(state_spaces)
-        });
-    // Once the MCTS is done, we can select the best guess
-    select_best_word(&guess, &state_space, dict)
-}
-
-fn save_state_space_to_file(state_space: &StateSpace, filename: &Path) -> io::Result<()> {
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(filename)?;
-    for ((guess, word), node) in state_space {
-        writeln!(
-            &mut file,
-            "{},{},{},{},{},{}",
-            guess.to_string(),
-            word,
-            node.guess.to_string(),
-            node.cumulative_score,
-            node.num_simulations,
-            node.num_guess,
-        )
-        .unwrap();
+            let state_space = combine_state_spaces(state_spaces);
+            progress.advance(num_solutions, iterations, || {
+                best_so_far(&guess, &state_space, dict)
+            });
+            Ok::<_, Error>(state_space)
+        },
+    )?;
+    save_pending(store, &guess, &mut pending)?;
+    if is_stopped(cancel) {
+        progress.finish("interrupted");
+        return Err(Error::Interrupted);
     }
-    file.sync_all()?;
-    Ok(())
+    // Once the MCTS is done, we can rank the guesses
+    let mut result = rank_words(&guess, &state_space, dict, weights, cache, options.top)?;
+    progress.finish(&format!(
+        "best {} ({:.3})",
+        result.best().word,
+        result.best().mean_score
+    ));
+    result.elapsed_ms = start.elapsed().as_millis();
+    Ok(result)
 }
 
-fn compress_state_space(state_space: &Path, solution: &str) -> io::Result<()> {
-    process::Command::new("tar")
-        .args([
-            "-c",
-            "-I",
-            "zstd",
-            "-f",
-            &state_space
-                .join(&format!("{}.zst", solution))
-                .to_str()
-                .unwrap(),
-            "-C",
-            &state_space.to_str().unwrap(),
-            &format!("{}.csv", solution),
-        ])
-        .spawn()?
-        .wait()?;
-    fs::remove_file(&state_space.join(&format!("{}.csv", solution)))?;
+/// Save the trees explored from `root` since the last checkpoint, in one write.
+fn save_pending(
+    store: &StateStore,
+    root: &Guess,
+    pending: &mut Vec<(String, StateSpace, Run)>,
+) -> Result<()> {
+    store.save_solutions(
+        root,
+        pending
+            .iter_mut()
+            .map(|(solution, state, run)| (solution.as_str(), state, *run)),
+    )?;
+    pending.clear();
     Ok(())
 }
 
-fn uncompress_state_space(state_space: &Path, solution: &str) -> io::Result<()> {
-    process::Command::new("tar")
-        .args([
-            "-x",
-            "-I",
-            "zstd",
-            "-f",
-            &state_space
-                .join(&format!("{}.zst", solution))
-                .to_str()
-                .unwrap(),
-            "-C",
-            &state_space.to_str().unwrap(),
-        ])
-        .spawn()?
-        .wait()?;
-    Ok(())
-}
-
-fn load_state_space_from_file(filename: &Path) -> io::Result<StateSpace> {
-    let file = fs::File::open(filename)?;
-    let mut state_space = StateSpace::new();
-    for line in io::BufReader::new(file).lines() {
-        let line = line?;
-        let line: Vec<&str> = line.split(',').collect();
-        if line.len() < 6 {
-            continue;
-        }
-        let guess = Guess::from_string(line[0]);
-        let word = line[1].to_string();
-        let node_guess = Guess::from_string(line[2]);
-        let cumulative_score = line[3].parse::<f32>();
-        let num_simulations = line[4].parse::<u32>();
-        let num_guess = line[5].parse::<u8>();
-        if cumulative_score.is_err() || num_simulations.is_err() || num_guess.is_err() {
-            continue;
-        }
-        let cumulative_score = cumulative_score.unwrap();
-        let num_simulations = num_simulations.unwrap();
-        let num_guess = num_guess.unwrap();
-        state_space.insert(
-            (guess, word.to_string()),
-            Node {
-                guess: node_guess,
-                cumulative_score,
-                num_simulations,
-                num_guess,
-            },
-        );
-    }
-    Ok(state_space)
+/// The word with the best mean score so far, for progress reports.
+fn best_so_far(guess: &Guess, state_space: &StateSpace, dict: &[String]) -> String {
+    guess
+        .solutions(dict)
+        .into_iter()
+        .filter_map(|word| {
+            let node = state_space.get(&(guess.clone(), word.clone()))?;
+            (node.num_simulations > 0)
+                .then(|| (word, node.cumulative_score / node.num_simulations as f32))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(String::new(), |(word, score)| {
+            format!("best {} ({:.3})", word, score)
+        })
 }
 
 fn combine_state_spaces(state_spaces: Vec<StateSpace>) -> StateSpace {
@@ -214,6 +362,7 @@ fn combine_state_spaces(state_spaces: Vec<StateSpace>) -> StateSpace {
             if let Some(node) = combined_state_space.get_mut(&key) {
                 node.cumulative_score += new_node.cumulative_score;
                 node.num_simulations += new_node.num_simulations;
+                node.num_wins += new_node.num_wins;
             } else {
                 combined_state_space.insert(key, new_node);
             }
@@ -222,29 +371,115 @@ fn combine_state_spaces(state_spaces: Vec<StateSpace>) -> StateSpace {
     combined_state_space
 }
 
-fn select_best_word(initial_guess: &Guess, state_space: &StateSpace, dict: &[String]) -> String {
-    initial_guess
-        .solutions(dict)
-        .into_iter()
-        .map(|word| {
-            let state = state_space
-                .get(&(initial_guess.clone(), word.clone()))
-                .unwrap();
-            (word, state.cumulative_score / state.num_simulations as f32)
-        })
-        .fold(
-            (String::new(), -1000.0),
-            |(best_word, best_score), (word, score)| {
-                if score > best_score {
-                    (word, score)
-                } else {
-                    (best_word, best_score)
-                }
-            },
-        )
-        .0
+fn rank_words(
+    initial_guess: &Guess,
+    state_space: &StateSpace,
+    dict: &[String],
+    weights: &[f32],
+    cache: &PatternTable,
+    top: usize,
+) -> Result<SearchResult> {
+    let mut suggestions = Vec::new();
+    for word in initial_guess.solutions(dict) {
+        let state = match state_space.get(&(initial_guess.clone(), word.clone())) {
+            Some(state) if state.num_simulations > 0 => state,
+            _ => continue,
+        };
+        suggestions.push(Suggestion {
+            mean_score: state.cumulative_score / state.num_simulations as f32,
+            visits: state.num_simulations,
+            win_probability: state.num_wins as f32 / state.num_simulations as f32,
+            expected_remaining: 0.0,
+            is_candidate: initial_guess.contains(&word, dict),
+            word,
+        });
+    }
+    if suggestions.is_empty() {
+        return Err(Error::Search("no word was simulated".to_string()));
+    }
+    suggestions.sort_by(|a, b| b.mean_score.total_cmp(&a.mean_score));
+    suggestions.truncate(top.max(1));
+    // Only worth computing for the words actually reported
+    for suggestion in &mut suggestions {
+        suggestion.expected_remaining =
+            initial_guess.expected_remaining(&suggestion.word, dict, weights, cache);
+    }
+    Ok(SearchResult {
+        candidates: initial_guess.num_solutions(),
+        simulations: state_space
+            .get(&(initial_guess.clone(), "".to_string()))
+            .map_or(0, |root| root.num_simulations),
+        elapsed_ms: 0,
+        suggestions,
+        from_book: false,
+    })
+}
+
+/// How many iterations one solution gets and how its tree is saved meanwhile.
+struct Budget<'a> {
+    run: Run,
+    deadline: Option<Instant>,
+    checkpoint: Option<Duration>,
+    max_nodes: Option<usize>,
+    cancel: Option<&'a AtomicBool>,
+    store: &'a StateStore,
 }
 
+/// Root node for a search from `guess`, which an earlier search may have
+/// reached deeper in its tree: the statistics of its children, already
+/// explored, carry over so the search continues from them.
+fn reroot(state_space: &StateSpace, guess: &Guess, num_guess: u8) -> Node {
+    let children = state_space
+        .iter()
+        .filter(|((parent, word), _)| parent == guess && !word.is_empty())
+        .map(|(_, node)| node);
+    children.fold(
+        Node {
+            guess: guess.clone(),
+            cumulative_score: 0.0,
+            num_simulations: 0,
+            num_wins: 0,
+            num_guess,
+            dirty: true,
+        },
+        |mut root, child| {
+            root.cumulative_score += child.cumulative_score;
+            root.num_simulations += child.num_simulations;
+            root.num_wins += child.num_wins;
+            root
+        },
+    )
+}
+
+/// Save the tree of `solution`, then drop its least visited nodes until it
+/// holds three quarters of `max_nodes` below the root's children.
+///
+/// The root and its children stay, as the ranking needs them.
+fn spill(
+    state_space: &mut StateSpace,
+    root: &Guess,
+    solution: &str,
+    run: Run,
+    max_nodes: usize,
+    store: &StateStore,
+) -> Result<()> {
+    store.save_solution(solution, state_space, root, run)?;
+    let mut deep: Vec<(u32, (Guess, String))> = state_space
+        .iter()
+        .filter(|((parent, _), _)| parent != root)
+        .map(|(key, node)| (node.num_simulations, key.clone()))
+        .collect();
+    let excess = deep.len().saturating_sub(max_nodes * 3 / 4);
+    deep.sort_unstable_by_key(|(num_simulations, _)| *num_simulations);
+    for (_, key) in deep.into_iter().take(excess) {
+        state_space.remove(&key);
+    }
+    Ok(())
+}
+
+/// Run the iterations of `budget` left on the tree of `solution`, returning
+/// the tree and how far the run went.
+#[allow(clippy::too_many_arguments)]
 fn explore_one_solution(
     mut state_space: StateSpace,
     guess: Guess,
@@ -252,35 +487,72 @@ fn explore_one_solution(
     num_guess: u8,
     max_guess: u8,
     dict: &[String],
-    num_iterations: usize,
-    cache: &HashMap<(String, String), Vec<FeedBack>>,
-) -> StateSpace {
-    let dict = &dict[..];
-    let root = Node {
-        guess: guess.clone(),
-        cumulative_score: 0.0,
-        num_simulations: 0,
-        num_guess,
-    };
-    state_space.insert((guess.clone(), "".to_string()), root.clone());
-    // Insert initial children
+    weights: &[f32],
+    budget: Budget,
+    cache: &PatternTable,
+) -> Result<(StateSpace, Run)> {
+    let mut run = budget.run;
+    let mut last_checkpoint = Instant::now();
+    let root_key = (guess.clone(), "".to_string());
+    // Insert initial children, keeping the statistics of nodes loaded from the store
     for word in guess.solutions(dict) {
-        let feedback = FeedBack::evaluate(&word, &solution, cache);
-        state_space.insert(
-            (guess.clone(), word.clone()),
-            Node {
-                guess: guess.refine(&word, &feedback, dict),
+        let key = (guess.clone(), word.clone());
+        if let Entry::Vacant(entry) = state_space.entry(key) {
+            let feedback = FeedBack::evaluate(&word, &solution, cache);
+            entry.insert(Node {
+                guess: guess.refine(&word, &feedback, dict)?,
                 cumulative_score: 0.0,
                 num_simulations: 0,
+                num_wins: 0,
                 num_guess: num_guess + 1,
-            },
-        );
+                dirty: true,
+            });
+        }
     }
-    for _ in 0..num_iterations {
-        //println!("{} {}/{}", solution, i + 1, num_iterations);
+    if !state_space.contains_key(&root_key) {
+        let root = reroot(&state_space, &guess, num_guess);
+        state_space.insert(root_key.clone(), root);
+    }
+    // The root and its children are never spilled, so only the nodes below count
+    let first_level = state_space
+        .keys()
+        .filter(|(parent, _)| *parent == guess)
+        .count();
+    let start = run.done;
+    for i in start..run.planned {
+        if is_stopped(budget.cancel)
+            || (i > start
+                && budget
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline))
+        {
+            break;
+        }
+        if budget
+            .checkpoint
+            .is_some_and(|every| last_checkpoint.elapsed() >= every)
+        {
+            budget
+                .store
+                .save_solution(&solution, &mut state_space, &guess, run)?;
+            last_checkpoint = Instant::now();
+        }
+        if let Some(max_nodes) = budget
+            .max_nodes
+            .filter(|&max| state_space.len() - first_level > max)
+        {
+            spill(
+                &mut state_space,
+                &guess,
+                &solution,
+                run,
+                max_nodes,
+                budget.store,
+            )?;
+        }
         // One iteration of MCTS
-        let mut sequence = vec![(root.guess.clone(), "".to_string())];
-        let mut current_node = &root;
+        let mut sequence = vec![root_key.clone()];
+        let mut current_node = state_space.get(&root_key).unwrap();
         // Selection
         loop {
             let children_words = current_node.guess.solutions(dict);
@@ -305,7 +577,7 @@ fn explore_one_solution(
                     ((current_node, ""), -1.0),
                     |(acc, max_score), ((node, w), score)| {
                         if score > max_score {
-                            ((&node, w), score)
+                            ((node, w), score)
                         } else {
                             (acc, max_score)
                         }
@@ -316,28 +588,34 @@ fn explore_one_solution(
         }
         let leaf_guess = current_node.guess.clone();
         let leaf_num_guess = current_node.num_guess;
-        drop(current_node);
         if leaf_num_guess < max_guess && leaf_guess.num_solutions() > 1 {
-            // Expand the leaf node
+            // Expand the leaf node, with the statistics of children spilled before
+            if budget.max_nodes.is_some() {
+                for (key, node) in budget
+                    .store
+                    .load_children(&solution, &leaf_guess, dict.len())?
+                {
+                    state_space.entry(key).or_insert(node);
+                }
+            }
             for word in leaf_guess.solutions(dict) {
-                let feedback = FeedBack::evaluate(&word, &solution, cache);
-                let new_guess = leaf_guess.refine(&word, &feedback, dict);
-                state_space.insert(
-                    (leaf_guess.clone(), word.clone()),
-                    Node {
-                        guess: new_guess,
+                let key = (leaf_guess.clone(), word.clone());
+                if let Entry::Vacant(entry) = state_space.entry(key) {
+                    let feedback = FeedBack::evaluate(&word, &solution, cache);
+                    entry.insert(Node {
+                        guess: leaf_guess.refine(&word, &feedback, dict)?,
                         cumulative_score: 0.0,
                         num_simulations: 0,
-                        num_guess: num_guess + 1,
-                    },
-                );
+                        num_wins: 0,
+                        num_guess: leaf_num_guess + 1,
+                        dirty: true,
+                    });
+                }
             }
-            // Randomly select a child node
+            // Randomly select a child node, likely answers first
             let child_word = leaf_guess
-                .solutions(dict)
-                .choose(&mut thread_rng())
-                .unwrap()
-                .to_string();
+                .choose_weighted(dict, weights, &mut thread_rng())
+                .unwrap();
             sequence.push((leaf_guess.clone(), child_word.clone()));
             // Simulation
             let mut simulation_node = state_space
@@ -346,25 +624,27 @@ fn explore_one_solution(
                 .clone();
             while simulation_node.num_guess < max_guess && simulation_node.guess.num_solutions() > 1
             {
-                let children_words = simulation_node.guess.solutions(dict);
-                let child_word = children_words
-                    .choose(&mut thread_rng())
-                    .unwrap()
-                    .to_string();
+                let child_word = simulation_node
+                    .guess
+                    .choose_weighted(dict, weights, &mut thread_rng())
+                    .unwrap();
 
                 let next_guess = simulation_node.guess.refine(
                     &child_word,
                     &FeedBack::evaluate(&child_word, &solution, cache),
                     dict,
-                );
+                )?;
                 simulation_node = Node {
                     guess: next_guess,
                     cumulative_score: 0.0,
                     num_simulations: 0,
+                    num_wins: 0,
                     num_guess: simulation_node.num_guess + 1,
+                    dirty: false,
                 };
             }
-            if simulation_node.guess.num_solutions() == 1 {
+            let won = simulation_node.guess.num_solutions() == 1;
+            if won {
                 // Simulation won
                 simulation_node.cumulative_score =
                     max_guess as f32 - simulation_node.num_guess as f32;
@@ -378,11 +658,14 @@ fn explore_one_solution(
                 let node = state_space.get_mut(&(guess.clone(), word.clone())).unwrap();
                 node.cumulative_score += simulation_node.cumulative_score;
                 node.num_simulations += 1;
+                node.num_wins += won as u32;
+                node.dirty = true;
             }
         } else {
             // Leaf node is terminal, we immediately backpropagate
             let mut cumulative_score = 0.0;
-            if leaf_guess.num_solutions() == 1 {
+            let won = leaf_guess.num_solutions() == 1;
+            if won {
                 // Simulation won
                 cumulative_score = max_guess as f32 - leaf_num_guess as f32;
             }
@@ -392,8 +675,369 @@ fn explore_one_solution(
                 let node = state_space.get_mut(&(guess.clone(), word.clone())).unwrap();
                 node.cumulative_score += cumulative_score;
                 node.num_simulations += 1;
+                node.num_wins += won as u32;
+                node.dirty = true;
             }
         }
+        run.done = i + 1;
+    }
+    Ok((state_space, run))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::temp_store;
+
+    fn dict() -> Vec<String> {
+        [
+            "crane", "crate", "trace", "react", "cater", "caret", "slate", "plate", "grace",
+            "brace", "trade", "grade", "stare", "share", "spare", "scare", "snare", "irate",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    /// Enough solutions that exploring them all takes far longer than the
+    /// tests wait, each spelled by the base 26 digits of a distinct multiple.
+    fn many_words() -> Vec<String> {
+        (0..100u64)
+            .map(|i| {
+                let spelling = i * 2_654_435_761 % 26u64.pow(5);
+                (0..5)
+                    .map(|k| (b'a' + (spelling / 26u64.pow(k) % 26) as u8) as char)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn explore(
+        store: &StateStore,
+        run: Run,
+        deadline: Option<Instant>,
+        max_nodes: Option<usize>,
+    ) -> (StateSpace, Run) {
+        let dict = dict();
+        let budget = Budget {
+            run,
+            deadline,
+            checkpoint: None,
+            max_nodes,
+            cancel: None,
+            store,
+        };
+        explore_one_solution(
+            StateSpace::new(),
+            Guess::new(&dict),
+            "crane".to_string(),
+            0,
+            6,
+            &dict,
+            &vec![1.0; dict.len()],
+            budget,
+            &PatternTable::new(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn words_are_ranked_by_mean_score_and_cut_to_the_top() {
+        let dict = dict();
+        let root = Guess::new(&dict);
+        let node = |word: &str, cumulative_score: f32, num_simulations: u32, num_wins: u32| {
+            (
+                (root.clone(), word.to_string()),
+                Node {
+                    guess: root
+                        .refine(word, &FeedBack::compute(word, "slate"), &dict)
+                        .unwrap(),
+                    cumulative_score,
+                    num_simulations,
+                    num_wins,
+                    num_guess: 1,
+                    dirty: false,
+                },
+            )
+        };
+        let state_space = StateSpace::from([
+            node("crane", 6.0, 4, 3),
+            node("slate", 10.0, 4, 4),
+            node("plate", 4.0, 2, 1),
+            // Never simulated, so never suggested
+            node("trace", 0.0, 0, 0),
+        ]);
+        let weights = vec![1.0; dict.len()];
+        let cache = PatternTable::new();
+        let result = rank_words(&root, &state_space, &dict, &weights, &cache, 2).unwrap();
+        let words: Vec<&str> = result.suggestions.iter().map(|s| s.word.as_str()).collect();
+        assert_eq!(words, ["slate", "plate"]);
+        assert_eq!(result.best().mean_score, 2.5);
+        assert_eq!(result.best().win_probability, 1.0);
+        assert_eq!(result.suggestions[1].visits, 2);
+        assert!(result.suggestions.iter().all(|s| s.is_candidate));
+        assert_eq!(result.candidates, dict.len());
+
+        // A top of 0 still gives the best word
+        let best = rank_words(&root, &state_space, &dict, &weights, &cache, 0).unwrap();
+        assert_eq!(best.suggestions.len(), 1);
+        assert!(rank_words(&root, &StateSpace::new(), &dict, &weights, &cache, 5).is_err());
+    }
+
+    #[test]
+    fn reroot_carries_over_the_children_explored_before() {
+        let dict = dict();
+        let root = Guess::new(&dict);
+        let child = root
+            .refine("slate", &FeedBack::compute("slate", "crane"), &dict)
+            .unwrap();
+        let node = |guess: &Guess, cumulative_score: f32, num_simulations: u32| Node {
+            guess: guess.clone(),
+            cumulative_score,
+            num_simulations,
+            num_wins: num_simulations,
+            num_guess: 2,
+            dirty: false,
+        };
+        let state_space = StateSpace::from([
+            ((root.clone(), String::new()), node(&root, 30.0, 10)),
+            ((root.clone(), "slate".to_string()), node(&child, 12.0, 4)),
+            ((child.clone(), "crane".to_string()), node(&child, 8.0, 2)),
+            ((child.clone(), "crate".to_string()), node(&child, 3.0, 1)),
+            // A root saved by an earlier search from the child is not a child
+            ((child.clone(), String::new()), node(&child, 50.0, 20)),
+        ]);
+        let rerooted = reroot(&state_space, &child, 1);
+        assert_eq!(rerooted.guess, child);
+        assert_eq!(rerooted.num_guess, 1);
+        assert_eq!(rerooted.num_simulations, 3);
+        assert_eq!(rerooted.num_wins, 3);
+        assert_eq!(rerooted.cumulative_score, 11.0);
+        assert!(rerooted.dirty);
+        // Nothing explored yet from a set never reached
+        let fresh = reroot(&StateSpace::new(), &child, 1);
+        assert_eq!(fresh.num_simulations, 0);
+    }
+
+    #[test]
+    fn tree_children_are_the_positions_the_solver_reaches() {
+        let store = temp_store();
+        let (state_space, _) = explore(&store, Run::new(50), None, None);
+        let solver = crate::solver::Solver::new(
+            dict(),
+            vec![1.0; dict().len()],
+            PatternTable::new(),
+            temp_store(),
+            SearchOptions::default(),
+        );
+        let mut checked = 0;
+        for ((parent, word), node) in state_space.iter().filter(|((_, word), _)| !word.is_empty()) {
+            let feedback = FeedBack::compute(word, "crane");
+            assert_eq!(solver.refine(parent, word, &feedback).unwrap(), node.guess);
+            checked += 1;
+        }
+        assert!(checked > dict().len());
+    }
+
+    #[test]
+    fn past_deadline_runs_a_single_iteration() {
+        let store = temp_store();
+        let (_, run) = explore(&store, Run::new(100), Some(Instant::now()), None);
+        assert_eq!(
+            run,
+            Run {
+                done: 1,
+                planned: 100
+            }
+        );
+    }
+
+    #[test]
+    fn resumed_run_does_only_the_remaining_iterations() {
+        let store = temp_store();
+        let (state_space, run) = explore(
+            &store,
+            Run {
+                done: 40,
+                planned: 100,
+            },
+            None,
+            None,
+        );
+        assert_eq!(
+            run,
+            Run {
+                done: 100,
+                planned: 100
+            }
+        );
+        let root = &state_space[&(Guess::new(&dict()), String::new())];
+        assert_eq!(root.num_simulations, 60);
+    }
+
+    #[test]
+    fn node_budget_below_the_fan_out_spills_only_deeper_nodes() {
+        let store = temp_store();
+        let (state_space, _) = explore(&store, Run::new(50), None, Some(2));
+        let root = Guess::new(&dict());
+        let first_level = state_space
+            .keys()
+            .filter(|(parent, _)| *parent == root)
+            .count();
+        // The root and one child per word stay in memory, unlike the deeper nodes
+        assert_eq!(first_level, dict().len() + 1);
+        assert_eq!(
+            state_space[&(root.clone(), String::new())].num_simulations,
+            50
+        );
+        let saved = store.load_solution("crane", &root, dict().len()).unwrap();
+        assert!(saved.len() > state_space.len());
+    }
+
+    #[test]
+    fn search_records_the_run_for_resuming() {
+        let store = temp_store();
+        let dict = dict();
+        let options = SearchOptions {
+            iterations: 20,
+            threads: 2,
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        let guess = Guess::new(&dict);
+        let weights = vec![1.0; dict.len()];
+        let cache = PatternTable::new();
+        search(guess.clone(), 0, &dict, &weights, &cache, &store, &options).unwrap();
+        let run = store.load_run("crane", &guess).unwrap();
+        assert_eq!(
+            run,
+            Some(Run {
+                done: 20,
+                planned: 20
+            })
+        );
+        // Resuming a finished search runs nothing more
+        let resumed = SearchOptions {
+            resume: true,
+            ..options
+        };
+        let result = search(guess.clone(), 0, &dict, &weights, &cache, &store, &resumed).unwrap();
+        assert_eq!(result.simulations, 20 * dict.len() as u32);
+    }
+
+    #[test]
+    fn cancelled_search_stops_without_a_result() {
+        let store = temp_store();
+        let dict = dict();
+        let guess = Guess::new(&dict);
+        let options = SearchOptions {
+            iterations: 1000,
+            verbosity: Verbosity::Quiet,
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..SearchOptions::default()
+        };
+        let weights = vec![1.0; dict.len()];
+        let result = search(
+            guess.clone(),
+            0,
+            &dict,
+            &weights,
+            &PatternTable::new(),
+            &store,
+            &options,
+        );
+        assert!(matches!(result, Err(Error::Interrupted)));
+        assert_eq!(store.load_run("crane", &guess).unwrap(), None);
+    }
+
+    #[test]
+    fn interrupted_search_saves_what_it_explored() {
+        let store = temp_store();
+        let dict = many_words();
+        let guess = Guess::new(&dict);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let options = SearchOptions {
+            iterations: 1000,
+            threads: 2,
+            verbosity: Verbosity::Quiet,
+            // Only the interruption makes the search save
+            checkpoint: None,
+            cancel: Some(cancel.clone()),
+            ..SearchOptions::default()
+        };
+        let result = thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(50));
+                cancel.store(true, Ordering::SeqCst);
+            });
+            search(
+                guess.clone(),
+                0,
+                &dict,
+                &vec![1.0; dict.len()],
+                &PatternTable::new(),
+                &store,
+                &options,
+            )
+        });
+        assert!(matches!(result, Err(Error::Interrupted)));
+        let saved: Vec<Run> = dict
+            .iter()
+            .filter_map(|word| store.load_run(word, &guess).unwrap())
+            .collect();
+        assert!(!saved.is_empty() && saved.len() < dict.len());
+        assert!(saved
+            .iter()
+            .any(|run| run.done > 0 && run.done < run.planned));
+    }
+
+    #[test]
+    fn zero_time_limit_explores_only_the_first_solutions_once() {
+        let store = temp_store();
+        let dict = dict();
+        let options = SearchOptions {
+            iterations: 1000,
+            threads: 2,
+            time_limit: Some(Duration::ZERO),
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        let result = search(
+            Guess::new(&dict),
+            0,
+            &dict,
+            &vec![1.0; dict.len()],
+            &PatternTable::new(),
+            &store,
+            &options,
+        )
+        .unwrap();
+        // One chunk of solutions, one per thread
+        assert_eq!(result.simulations, 2);
+    }
+
+    #[test]
+    fn time_limit_bounds_the_search() {
+        let store = temp_store();
+        let dict = many_words();
+        let options = SearchOptions {
+            iterations: 1000,
+            threads: 2,
+            time_limit: Some(Duration::from_millis(50)),
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        let start = Instant::now();
+        let result = search(
+            Guess::new(&dict),
+            0,
+            &dict,
+            &vec![1.0; dict.len()],
+            &PatternTable::new(),
+            &store,
+            &options,
+        )
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_millis(250));
+        assert!(result.simulations > 0);
     }
-    return state_space;
 }
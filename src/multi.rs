@@ -0,0 +1,413 @@
+use crate::cache::PatternTable;
+use crate::error::{Error, Result};
+use crate::game::{self, Game};
+use crate::guess::{FeedBack, Guess};
+use serde::Serialize;
+
+/// Number of guesses allowed when playing `boards` boards at once, as in
+/// Wordle, Dordle, Quordle, Octordle and Sedecordle.
+pub fn max_guess(boards: usize) -> Result<u8> {
+    match boards {
+        1 => Ok(6),
+        2 => Ok(7),
+        4 => Ok(9),
+        8 => Ok(13),
+        16 => Ok(21),
+        _ => Err(Error::InvalidInput(format!(
+            "{} boards is not supported, use 1, 2, 4, 8 or 16",
+            boards
+        ))),
+    }
+}
+
+/// A word worth playing on several boards at once.
+#[derive(Clone, Debug, Serialize)]
+pub struct BoardSuggestion {
+    pub word: String,
+    /// Information, in bits, summed over the unsolved boards
+    pub information: f32,
+    /// Expected number of boards solved by the word itself
+    pub expected_solved: f32,
+}
+
+impl BoardSuggestion {
+    /// Solving a board outright is worth a full bit on top of the information gained.
+    pub fn score(&self) -> f32 {
+        self.information + self.expected_solved
+    }
+}
+
+/// One guess scored against every hidden word at once.
+#[derive(Clone, Debug)]
+pub struct MultiBoard {
+    boards: Vec<Guess>,
+    solved: Vec<bool>,
+    num_guess: u8,
+    max_guess: u8,
+}
+
+impl MultiBoard {
+    pub fn new(dict: &[String], boards: usize) -> Result<Self> {
+        Ok(Self {
+            boards: vec![Guess::new(dict); boards],
+            solved: vec![false; boards],
+            num_guess: 0,
+            max_guess: max_guess(boards)?,
+        })
+    }
+    /// Candidate set of each board.
+    pub fn boards(&self) -> &[Guess] {
+        &self.boards
+    }
+    /// Whether each board is solved.
+    pub fn solved(&self) -> &[bool] {
+        &self.solved
+    }
+    pub fn num_guess(&self) -> u8 {
+        self.num_guess
+    }
+    pub fn max_guess(&self) -> u8 {
+        self.max_guess
+    }
+    pub fn is_won(&self) -> bool {
+        self.solved.iter().all(|&solved| solved)
+    }
+    pub fn is_over(&self) -> bool {
+        self.is_won() || self.num_guess >= self.max_guess
+    }
+    /// Record playing `word`, with one feedback per board; the feedback of
+    /// boards already solved is ignored and may be `None`.
+    ///
+    /// Each board narrows down through [`Guess::refine`], like the solver's
+    /// candidates, so a board is the position a search of it would reach.
+    pub fn play(
+        &mut self,
+        word: &str,
+        feedbacks: &[Option<Vec<FeedBack>>],
+        dict: &[String],
+    ) -> Result<()> {
+        if feedbacks.len() != self.boards.len() {
+            return Err(Error::InvalidInput(format!(
+                "{} feedbacks given for {} boards",
+                feedbacks.len(),
+                self.boards.len()
+            )));
+        }
+        if self.is_over() {
+            return Err(Error::InvalidInput("the game is over".to_string()));
+        }
+        for (i, feedback) in feedbacks.iter().enumerate() {
+            if self.solved[i] {
+                continue;
+            }
+            let feedback = feedback.as_ref().ok_or_else(|| {
+                Error::InvalidInput(format!("missing feedback for board {}", i + 1))
+            })?;
+            if feedback.len() != word.chars().count() {
+                return Err(Error::InvalidInput(format!(
+                    "{} feedback tiles given for {:?} on board {}",
+                    feedback.len(),
+                    word,
+                    i + 1
+                )));
+            }
+            let board = self.boards[i].refine(word, feedback, dict)?;
+            if board.num_solutions() == 0 {
+                return Err(Error::InvalidInput(format!(
+                    "no word of the dictionary matches board {}",
+                    i + 1
+                )));
+            }
+            self.solved[i] = feedback.iter().all(|&fb| fb == FeedBack::Green);
+            self.boards[i] = board;
+        }
+        self.num_guess += 1;
+        Ok(())
+    }
+    /// Rank the words still possible on some unsolved board by the
+    /// information they give on all of them.
+    ///
+    /// A board down to a single candidate is always worth solving first.
+    pub fn suggest(
+        &self,
+        dict: &[String],
+        weights: &[f32],
+        cache: &PatternTable,
+        top: usize,
+    ) -> Vec<BoardSuggestion> {
+        let unsolved: Vec<&Guess> = self
+            .boards
+            .iter()
+            .zip(&self.solved)
+            .filter(|(_, &solved)| !solved)
+            .map(|(board, _)| board)
+            .collect();
+        // Scoring every word of the dictionary on every board costs too much
+        let mut possible = vec![false; dict.len()];
+        for i in unsolved.iter().flat_map(|board| board.indices()) {
+            possible[i] = true;
+        }
+        let mut suggestions: Vec<BoardSuggestion> = dict
+            .iter()
+            .zip(weights)
+            .zip(possible)
+            .filter(|(_, possible)| *possible)
+            .map(|((word, &weight), _)| BoardSuggestion {
+                word: word.clone(),
+                information: unsolved
+                    .iter()
+                    .map(|board| board.entropy(word, dict, weights, cache))
+                    .sum(),
+                expected_solved: unsolved
+                    .iter()
+                    .filter(|board| board.contains(word, dict))
+                    .map(|board| weight / board.weight(weights))
+                    .fold(0.0, |acc, p| acc + p),
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            let forced = |s: &BoardSuggestion| s.expected_solved >= 1.0;
+            forced(b)
+                .cmp(&forced(a))
+                .then(b.score().total_cmp(&a.score()))
+        });
+        suggestions.truncate(top.max(1));
+        suggestions
+    }
+}
+
+/// One turn of a multi-board game: the word played and the feedback of each
+/// board, `None` for boards solved before the turn.
+#[derive(Clone, Debug)]
+pub struct Turn {
+    pub word: String,
+    pub feedbacks: Vec<Option<Vec<FeedBack>>>,
+}
+
+impl Turn {
+    /// Parse `word:pattern,pattern,...`, one pattern per board and `-` for boards already solved.
+    pub fn parse(entry: &str) -> Result<Self> {
+        let (word, patterns) = entry.split_once(':').ok_or_else(|| {
+            Error::InvalidInput(format!("expected word:pattern,..., got {:?}", entry))
+        })?;
+        let feedbacks = patterns
+            .split(',')
+            .map(|pattern| match pattern {
+                "-" => Ok(None),
+                pattern => FeedBack::parse(pattern).map(Some),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            word: word.to_string(),
+            feedbacks,
+        })
+    }
+    /// Patterns of the turn in the format read by [`Turn::parse`].
+    pub fn patterns(&self) -> Vec<String> {
+        self.feedbacks
+            .iter()
+            .map(|feedback| match feedback {
+                Some(feedback) => feedback.iter().map(|fb| fb.to_char()).collect(),
+                None => "-".to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Let the solver play against `secrets`, one per board, calling `on_turn` after every guess.
+pub fn self_play(
+    dict: &[String],
+    weights: &[f32],
+    cache: &PatternTable,
+    secrets: &[String],
+    mut on_turn: impl FnMut(&MultiBoard, &Turn),
+) -> Result<MultiBoard> {
+    let mut game = MultiBoard::new(dict, secrets.len())?;
+    let mut referees = secrets
+        .iter()
+        .map(|secret| Game::new(secret, dict, game.max_guess(), false))
+        .collect::<Result<Vec<Game>>>()?;
+    while !game.is_over() {
+        let word = game.suggest(dict, weights, cache, 1).remove(0).word;
+        let feedbacks = referees
+            .iter_mut()
+            .map(|referee| {
+                if referee.is_won() {
+                    Ok(None)
+                } else {
+                    referee.play(&word, dict, cache).map(Some)
+                }
+            })
+            .collect::<Result<Vec<Option<Vec<FeedBack>>>>>()?;
+        game.play(&word, &feedbacks, dict)?;
+        on_turn(&game, &Turn { word, feedbacks });
+    }
+    Ok(game)
+}
+
+/// Let the solver play `games` games of `boards` boards against answers drawn
+/// with the prior `weights`. `on_turn` is called after every guess, with the
+/// number of the game, and `on_game` after every game, with its answers and
+/// turns. Returns the number of games won and the guesses they took.
+#[allow(clippy::too_many_arguments)]
+pub fn self_play_games(
+    dict: &[String],
+    weights: &[f32],
+    cache: &PatternTable,
+    boards: usize,
+    games: usize,
+    mut on_turn: impl FnMut(usize, &MultiBoard, &Turn),
+    mut on_game: impl FnMut(usize, &[String], &MultiBoard, &[Turn]),
+) -> Result<(usize, usize)> {
+    let (mut won, mut total_guesses) = (0, 0);
+    for i in 0..games {
+        let secrets = game::draw_answers(dict, weights, boards)?;
+        let mut turns = Vec::new();
+        let game = self_play(dict, weights, cache, &secrets, |game, turn| {
+            on_turn(i, game, turn);
+            turns.push(turn.clone());
+        })?;
+        on_game(i, &secrets, &game, &turns);
+        if game.is_won() {
+            won += 1;
+            total_guesses += game.num_guess() as usize;
+        }
+    }
+    Ok((won, total_guesses))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::SearchOptions;
+    use crate::solver::Solver;
+    use crate::store::tests::temp_store;
+
+    fn dict() -> Vec<String> {
+        ["crane", "crate", "trace", "slate", "plate", "pious"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn turns_round_trip_and_solved_boards_are_skipped() {
+        let dict = dict();
+        let mut game = MultiBoard::new(&dict, 2).unwrap();
+        assert_eq!(game.max_guess(), 7);
+        let turn = Turn::parse("crane:ggggg,bbgbg").unwrap();
+        assert_eq!(turn.patterns(), ["ggggg", "bbgbg"]);
+        game.play(&turn.word, &turn.feedbacks, &dict).unwrap();
+        assert_eq!(game.solved(), [true, false]);
+        assert_eq!(game.boards()[1].solutions(&dict), ["slate", "plate"]);
+        // The solved board needs no feedback anymore
+        let turn = Turn::parse("slate:-,ggggg").unwrap();
+        assert_eq!(turn.patterns(), ["-", "ggggg"]);
+        game.play(&turn.word, &turn.feedbacks, &dict).unwrap();
+        assert!(game.is_won() && game.is_over());
+        assert_eq!(game.num_guess(), 2);
+    }
+
+    #[test]
+    fn plays_are_checked_against_every_board() {
+        let dict = dict();
+        let mut game = MultiBoard::new(&dict, 2).unwrap();
+        let feedback = Some(FeedBack::parse("bbbbb").unwrap());
+        assert!(game
+            .play("crane", std::slice::from_ref(&feedback), &dict)
+            .is_err());
+        assert!(game
+            .play("crane", &[feedback.clone(), None], &dict)
+            .is_err());
+        assert!(game
+            .play(
+                "crane",
+                &[feedback.clone(), Some(FeedBack::parse("bbbb").unwrap())],
+                &dict
+            )
+            .is_err());
+        assert_eq!(game.num_guess(), 0);
+        assert!(MultiBoard::new(&dict, 3).is_err());
+        assert!(Turn::parse("crane").is_err());
+    }
+
+    #[test]
+    fn boards_narrow_to_the_positions_the_solver_reaches() {
+        let dict = dict();
+        let solver = Solver::new(
+            dict.clone(),
+            vec![1.0; dict.len()],
+            PatternTable::new(),
+            temp_store(),
+            SearchOptions::default(),
+        );
+        let mut game = MultiBoard::new(&dict, 2).unwrap();
+        let feedbacks = [
+            FeedBack::compute("pious", "slate"),
+            FeedBack::compute("pious", "crane"),
+        ];
+        game.play("pious", &feedbacks.clone().map(Some), &dict)
+            .unwrap();
+        for (board, feedback) in game.boards().iter().zip(&feedbacks) {
+            let guess = solver
+                .refine(&solver.new_game(), "pious", feedback)
+                .unwrap();
+            assert_eq!(*board, guess);
+        }
+    }
+
+    #[test]
+    fn a_board_left_with_one_word_is_solved_first() {
+        let dict = dict();
+        let weights = vec![1.0; dict.len()];
+        let mut game = MultiBoard::new(&dict, 2).unwrap();
+        let feedbacks = [
+            Some(FeedBack::compute("pious", "crane")),
+            Some(FeedBack::compute("pious", "slate")),
+        ];
+        game.play("pious", &feedbacks, &dict).unwrap();
+        let suggestions = game.suggest(&dict, &weights, &PatternTable::new(), 3);
+        assert_eq!(suggestions.len(), 3);
+        assert_eq!(game.boards()[1].solutions(&dict), ["slate"]);
+        assert_eq!(suggestions[0].word, "slate");
+        assert_eq!(suggestions[0].expected_solved, 1.0);
+        // Words ruled out on every board are not ranked
+        assert!(suggestions
+            .iter()
+            .all(|suggestion| !["pious", "plate"].contains(&suggestion.word.as_str())));
+        assert!(suggestions
+            .windows(2)
+            .all(|pair| pair[0].expected_solved >= 1.0 || pair[0].score() >= pair[1].score()));
+    }
+
+    #[test]
+    fn self_play_games_reports_every_turn_and_game() {
+        let dict = dict();
+        let weights = vec![1.0; dict.len()];
+        let (mut turns_seen, mut games_seen) = (0, Vec::new());
+        let (won, total_guesses) = self_play_games(
+            &dict,
+            &weights,
+            &PatternTable::new(),
+            2,
+            3,
+            |_, _, _| turns_seen += 1,
+            |i, secrets, game, turns| {
+                assert_eq!(secrets.len(), 2);
+                assert_eq!(turns.len(), game.num_guess() as usize);
+                games_seen.push((i, turns.len()));
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            games_seen.iter().map(|&(i, _)| i).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(
+            turns_seen,
+            games_seen.iter().map(|&(_, turns)| turns).sum::<usize>()
+        );
+        // Two boards of six words leave enough guesses to always win
+        assert_eq!(won, 3);
+        assert_eq!(total_guesses, turns_seen);
+    }
+}
@@ -0,0 +1,604 @@
+use clap::ArgEnum;
+use serde_json::json;
+use std::{
+    fmt,
+    io::{self, Write},
+    path::Path,
+    process,
+};
+use wordle_bot::{
+    absurdle::Host,
+    analysis::Analysis,
+    book::Book,
+    cache::CacheError,
+    constraints::Constraints,
+    dictionary::Report,
+    engine::Standing,
+    game::Game,
+    multi::{BoardSuggestion, MultiBoard, Turn},
+    share::Share,
+    Error, FeedBack, SearchResult,
+};
+
+/// How results are written to stdout. Progress always goes to stderr.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable tables
+    Text,
+    /// One pretty-printed JSON document
+    Json,
+    /// One JSON object per line, the only JSON for the commands printing as they go
+    Jsonl,
+}
+
+/// Like `println!`, without its panic once stdout is closed.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        write_line(format_args!($($arg)*))
+    };
+}
+
+/// Write one line to stdout through a locked handle.
+///
+/// A reader gone early, as `head` is once it has its lines, ends the program
+/// quietly. Any other failure exits as an I/O error would.
+fn write_line(line: fmt::Arguments) {
+    let mut stdout = io::stdout().lock();
+    if let Err(e) = stdout.write_fmt(line).and_then(|_| stdout.write_all(b"\n")) {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            process::exit(0);
+        }
+        let error = Error::Io(e);
+        eprintln!("error: {}", error);
+        process::exit(error.exit_code());
+    }
+}
+
+fn print_json(format: Format, value: &serde_json::Value) {
+    if format == Format::Json {
+        out!("{:#}", value);
+    } else {
+        out!("{}", value);
+    }
+}
+
+pub fn print_result(format: Format, result: &SearchResult, constraints: Option<&Constraints>) {
+    if format != Format::Text {
+        let mut value = json!({ "result": result });
+        if let Some(constraints) = constraints {
+            value["constraints"] = json!(constraints.to_string());
+        }
+        print_json(format, &value);
+        return;
+    }
+    if let Some(constraints) = constraints {
+        out!("{}", constraints);
+    }
+    let width = result
+        .suggestions
+        .iter()
+        .map(|s| s.word.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    if result.from_book {
+        out!("{} candidates, from the opening book", result.candidates);
+    } else {
+        out!("{} candidates", result.candidates);
+    }
+    out!(
+        "{:<width$}  {:>6}  {:>7}  {:>6}  {:>9}  {:>9}",
+        "word",
+        "score",
+        "visits",
+        "win",
+        "remaining",
+        "candidate",
+        width = width
+    );
+    for suggestion in &result.suggestions {
+        out!(
+            "{:<width$}  {:>6.3}  {:>7}  {:>5.1}%  {:>9.1}  {:>9}",
+            suggestion.word,
+            suggestion.mean_score,
+            suggestion.visits,
+            suggestion.win_probability * 100.0,
+            suggestion.expected_remaining,
+            if suggestion.is_candidate { "yes" } else { "no" },
+            width = width
+        );
+    }
+}
+
+pub fn print_cache_problems(format: Format, path: &Path, problems: &[CacheError]) {
+    if format != Format::Text {
+        let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        print_json(
+            format,
+            &json!({ "cache": path.display().to_string(), "problems": problems }),
+        );
+        return;
+    }
+    if problems.is_empty() {
+        out!("{}: ok", path.display());
+    }
+    for problem in problems {
+        out!("{}: {}", path.display(), problem);
+    }
+}
+
+pub fn print_error(format: Format, error: &Error) {
+    if format == Format::Text {
+        eprintln!("error: {}", error);
+        return;
+    }
+    print_json(format, &error_value(error));
+}
+
+fn error_value(error: &Error) -> serde_json::Value {
+    json!({
+        "error": {
+            "kind": error.kind(),
+            "message": error.to_string(),
+            "exit_code": error.exit_code(),
+        }
+    })
+}
+
+pub fn print_multi(format: Format, game: &MultiBoard, suggestions: &[BoardSuggestion]) {
+    let boards: Vec<_> = game
+        .boards()
+        .iter()
+        .zip(game.solved())
+        .map(|(board, &solved)| json!({ "candidates": board.num_solutions(), "solved": solved }))
+        .collect();
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "guesses": game.num_guess(),
+                "max_guess": game.max_guess(),
+                "boards": boards,
+                "suggestions": suggestions,
+            }),
+        );
+        return;
+    }
+    let candidates: Vec<String> = game
+        .boards()
+        .iter()
+        .zip(game.solved())
+        .map(|(board, &solved)| {
+            if solved {
+                "solved".to_string()
+            } else {
+                board.num_solutions().to_string()
+            }
+        })
+        .collect();
+    out!(
+        "guess {}/{}, candidates per board: {}",
+        game.num_guess(),
+        game.max_guess(),
+        candidates.join(" ")
+    );
+    if suggestions.is_empty() {
+        return;
+    }
+    out!("{:<8}  {:>11}  {:>8}", "word", "information", "solves");
+    for suggestion in suggestions {
+        out!(
+            "{:<8}  {:>11.3}  {:>8.3}",
+            suggestion.word,
+            suggestion.information,
+            suggestion.expected_solved
+        );
+    }
+}
+
+pub fn print_multi_turn(format: Format, game_index: usize, game: &MultiBoard, turn: &Turn) {
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "game": game_index,
+                "guess": game.num_guess(),
+                "word": turn.word,
+                "feedback": turn.patterns(),
+            }),
+        );
+        return;
+    }
+    out!("{}  {}", turn.word, turn.patterns().join(" "));
+}
+
+pub fn print_multi_game(format: Format, game_index: usize, secrets: &[String], game: &MultiBoard) {
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "game": game_index,
+                "answers": secrets,
+                "won": game.is_won(),
+                "guesses": game.num_guess(),
+            }),
+        );
+        return;
+    }
+    out!(
+        "{} in {}/{} ({})",
+        if game.is_won() { "won" } else { "lost" },
+        game.num_guess(),
+        game.max_guess(),
+        secrets.join(" ")
+    );
+}
+
+pub fn print_self_play_summary(format: Format, games: usize, won: usize, total_guesses: usize) {
+    let average = if won > 0 {
+        total_guesses as f32 / won as f32
+    } else {
+        0.0
+    };
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({ "games": games, "won": won, "average_guesses": average }),
+        );
+        return;
+    }
+    out!(
+        "won {}/{} games, {:.2} guesses on average",
+        won,
+        games,
+        average
+    );
+}
+
+fn pattern(feedback: &[FeedBack]) -> String {
+    feedback.iter().map(|fb| fb.to_char()).collect()
+}
+
+pub fn print_absurdle_turn(format: Format, host: &Host, word: &str, feedback: &[FeedBack]) {
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "guess": host.num_guess(),
+                "word": word,
+                "feedback": pattern(feedback),
+                "candidates": host.candidates().num_solutions(),
+                "solved": host.is_solved(),
+            }),
+        );
+        return;
+    }
+    out!(
+        "{}  {}  {} candidates",
+        word,
+        pattern(feedback),
+        host.candidates().num_solutions()
+    );
+    if host.is_solved() {
+        out!("solved in {}", host.num_guess());
+    }
+}
+
+pub fn print_absurdle_suggestion(format: Format, host: &Host, word: &str) {
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "guesses": host.num_guess(),
+                "candidates": host.candidates().num_solutions(),
+                "suggestion": word,
+            }),
+        );
+        return;
+    }
+    out!(
+        "{} candidates, play {}",
+        host.candidates().num_solutions(),
+        word
+    );
+}
+
+pub fn print_dictionary_report(format: Format, report: &Report) {
+    if format != Format::Text {
+        print_json(format, &json!({ "dictionary": report }));
+        return;
+    }
+    let files: Vec<String> = report
+        .files
+        .iter()
+        .map(|f| f.display().to_string())
+        .collect();
+    out!("{}: {}", files.join(", "), report);
+    for (reason, examples) in &report.examples {
+        out!("  {}: {}", reason, examples.join(" "));
+    }
+}
+
+pub fn print_share(format: Format, text: &str) {
+    if format != Format::Text {
+        print_json(format, &json!({ "share": text }));
+        return;
+    }
+    out!("{}", text);
+}
+
+pub fn print_share_grid(format: Format, share: &Share) {
+    if format != Format::Text {
+        print_json(format, &json!({ "grid": share }));
+        return;
+    }
+    let header: Vec<String> = [share.game.clone(), share.puzzle.clone()]
+        .into_iter()
+        .flatten()
+        .collect();
+    if !header.is_empty() {
+        out!(
+            "{}: {} in {}{}",
+            header.join(" "),
+            if share.is_won() { "won" } else { "lost" },
+            share.rows.len(),
+            if share.hard_mode { ", hard mode" } else { "" }
+        );
+    }
+    for row in &share.rows {
+        out!("{}", pattern(row));
+    }
+}
+
+pub fn print_play(format: Format, game: &Game) {
+    let play = match game.history().last() {
+        Some(play) => play,
+        None => return,
+    };
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "guess": game.num_guess(),
+                "word": play.word,
+                "feedback": pattern(&play.feedback),
+            }),
+        );
+        return;
+    }
+    out!("{}  {}", play.word, pattern(&play.feedback));
+}
+
+pub fn print_game(format: Format, game: &Game) {
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "answer": game.secret(),
+                "won": game.is_won(),
+                "guesses": game.num_guess(),
+            }),
+        );
+        return;
+    }
+    if game.is_won() {
+        out!("won in {}/{}", game.num_guess(), game.max_guess());
+    } else {
+        out!("lost, the answer was {}", game.secret());
+    }
+}
+
+pub fn print_referee_game(format: Format, answer: &str, games: &[(Game, Option<String>)]) {
+    if format != Format::Text {
+        let games: Vec<serde_json::Value> = games
+            .iter()
+            .map(|(game, forfeit)| {
+                json!({
+                    "won": game.is_won(),
+                    "guesses": game.num_guess(),
+                    "words": game.history().iter().map(|play| &play.word).collect::<Vec<_>>(),
+                    "forfeit": forfeit,
+                })
+            })
+            .collect();
+        print_json(format, &json!({ "answer": answer, "games": games }));
+        return;
+    }
+    let results: Vec<String> = games
+        .iter()
+        .map(|(game, forfeit)| match forfeit {
+            Some(reason) => format!("forfeit ({})", reason),
+            None if game.is_won() => game.num_guess().to_string(),
+            None => "X".to_string(),
+        })
+        .collect();
+    out!("{}  {}", answer, results.join("  "));
+}
+
+pub fn print_referee_summary(format: Format, standings: &[Standing]) {
+    if format != Format::Text {
+        let standings: Vec<serde_json::Value> = standings
+            .iter()
+            .map(|s| {
+                let mut value = json!(s);
+                value["average_guesses"] = json!(s.average_guesses());
+                value
+            })
+            .collect();
+        print_json(format, &json!({ "standings": standings }));
+        return;
+    }
+    for (i, s) in standings.iter().enumerate() {
+        out!(
+            "{}. {}: won {}/{}, {:.2} guesses on average, best on {}, {} forfeits",
+            i + 1,
+            s.name,
+            s.won,
+            s.played,
+            s.average_guesses(),
+            s.best,
+            s.forfeits
+        );
+    }
+}
+
+pub fn print_analysis(format: Format, analysis: &Analysis) {
+    if format != Format::Text {
+        print_json(format, &json!({ "analysis": analysis }));
+        return;
+    }
+    out!(
+        "{:>4}  {:<7}  {:<8}  {:>6}  {:>6}  {:<7}  {:>8}  {:>8}  {:>6}  {:>5}  {:>4}",
+        "turn",
+        "word",
+        "feedback",
+        "before",
+        "after",
+        "best",
+        "expected",
+        "best",
+        "diff",
+        "skill",
+        "luck"
+    );
+    for (i, turn) in analysis.turns.iter().enumerate() {
+        out!(
+            "{:>4}  {:<7}  {:<8}  {:>6}  {:>6}  {:<7}  {:>8.2}  {:>8.2}  {:>+6.2}  {:>5}  {:>4}",
+            i + 1,
+            turn.word,
+            turn.feedback,
+            turn.candidates_before,
+            turn.candidates_after,
+            turn.best,
+            turn.expected_guesses,
+            turn.best_expected_guesses,
+            turn.difference,
+            turn.skill,
+            turn.luck
+        );
+    }
+    out!(
+        "skill {}, luck {}, {}",
+        analysis.skill,
+        analysis.luck,
+        if analysis.solved {
+            format!("solved in {}", analysis.turns.len())
+        } else {
+            "not solved".to_string()
+        }
+    );
+}
+
+pub fn print_book(format: Format, path: &Path, book: &Book) {
+    if format != Format::Text {
+        print_json(
+            format,
+            &json!({
+                "book": path.display().to_string(),
+                "version": book.version,
+                "fingerprint": book.fingerprint,
+                "depth": book.depth,
+                "entries": book.entries.len(),
+            }),
+        );
+        return;
+    }
+    out!(
+        "{}: {} positions down to {} guesses, opening with {}",
+        path.display(),
+        book.entries.len(),
+        book.depth,
+        book.entries
+            .get("")
+            .map_or("nothing", |result| result.best().word.as_str())
+    );
+}
+
+pub fn print_query(
+    format: Format,
+    constraints: &Constraints,
+    total: usize,
+    matches: &[(String, f32)],
+    show_weights: bool,
+) {
+    if format != Format::Text {
+        let matches: Vec<serde_json::Value> = matches
+            .iter()
+            .map(|(word, weight)| json!({ "word": word, "weight": weight }))
+            .collect();
+        print_json(
+            format,
+            &json!({
+                "constraints": constraints.to_string(),
+                "total": total,
+                "matches": matches,
+            }),
+        );
+        return;
+    }
+    for (word, weight) in matches {
+        if show_weights {
+            out!("{}  {:.3}", word, weight);
+        } else {
+            out!("{}", word);
+        }
+    }
+    if matches.len() < total {
+        // On stderr, so the list can be piped
+        eprintln!("... {} more", total - matches.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wordle_bot::Suggestion;
+
+    #[test]
+    fn formats_are_named_on_the_command_line() {
+        let formats: Vec<Format> = ["text", "json", "jsonl"]
+            .iter()
+            .map(|name| Format::from_str(name, false).unwrap())
+            .collect();
+        assert_eq!(formats, [Format::Text, Format::Json, Format::Jsonl]);
+        assert!(Format::from_str("yaml", false).is_err());
+    }
+
+    #[test]
+    fn errors_carry_their_kind_and_exit_code() {
+        let value = error_value(&Error::InvalidInput(
+            "\"crane\" is not in the dictionary".into(),
+        ));
+        assert_eq!(value["error"]["kind"], "invalid_input");
+        assert_eq!(value["error"]["exit_code"], 64);
+        assert_eq!(
+            value["error"]["message"],
+            "invalid input: \"crane\" is not in the dictionary"
+        );
+    }
+
+    #[test]
+    fn results_keep_every_statistic_in_json() {
+        let result = SearchResult {
+            candidates: 2,
+            simulations: 10,
+            elapsed_ms: 3,
+            suggestions: vec![Suggestion {
+                word: "crane".to_string(),
+                mean_score: 4.5,
+                visits: 10,
+                win_probability: 1.0,
+                expected_remaining: 1.0,
+                is_candidate: true,
+            }],
+            from_book: false,
+        };
+        let value = json!({ "result": result });
+        assert_eq!(value["result"]["candidates"], 2);
+        assert_eq!(value["result"]["suggestions"][0]["word"], "crane");
+        assert_eq!(value["result"]["suggestions"][0]["mean_score"], 4.5);
+        assert_eq!(value["result"]["suggestions"][0]["is_candidate"], true);
+        let read: SearchResult = serde_json::from_value(value["result"].clone()).unwrap();
+        assert_eq!(read.best().visits, 10);
+    }
+}
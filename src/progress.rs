@@ -0,0 +1,163 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    time::{Duration, Instant},
+};
+
+/// How much is reported on stderr while working.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Verbosity {
+    /// Errors only
+    Quiet,
+    /// One progress line, redrawn in place
+    Normal,
+    /// A progress line for every step and details of the work
+    Verbose,
+}
+
+/// Time between two redraws of the progress line on a terminal.
+const REDRAW: Duration = Duration::from_millis(200);
+/// Time between two progress lines when stderr is a file or a pipe.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Progress of a long task on stderr: items done out of a total, the rate
+/// of work and the time left.
+///
+/// Items and work differ for the search, which counts solutions done but
+/// runs at a number of iterations per second.
+pub struct Progress {
+    verbosity: Verbosity,
+    label: String,
+    item: String,
+    work: String,
+    total_items: usize,
+    total_work: usize,
+    items: usize,
+    done: usize,
+    start: Instant,
+    last_draw: Option<Instant>,
+    terminal: bool,
+}
+
+fn duration(seconds: f32) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds / 60 % 60),
+    }
+}
+
+impl Progress {
+    /// Progress of `label` over `total_items` items named `item` holding
+    /// `total_work` units of work named `work`, such as 2315 solutions
+    /// holding 231500 iterations.
+    pub fn new(
+        verbosity: Verbosity,
+        label: &str,
+        (total_items, item): (usize, &str),
+        (total_work, work): (usize, &str),
+    ) -> Self {
+        Self {
+            verbosity,
+            label: label.to_string(),
+            item: item.to_string(),
+            work: work.to_string(),
+            total_items,
+            total_work,
+            items: 0,
+            done: 0,
+            start: Instant::now(),
+            last_draw: None,
+            terminal: io::stderr().is_terminal(),
+        }
+    }
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+    /// Record `items` more items holding `work` units of work done.
+    ///
+    /// `detail` is shown at the end of the line, such as the best move so
+    /// far, and only computed when the line is drawn.
+    pub fn advance(&mut self, items: usize, work: usize, detail: impl FnOnce() -> String) {
+        self.items += items;
+        self.done += work;
+        let interval = if self.terminal { REDRAW } else { LOG_INTERVAL };
+        let due = self.last_draw.is_none_or(|last| last.elapsed() >= interval);
+        if self.verbosity == Verbosity::Verbose || (self.verbosity == Verbosity::Normal && due) {
+            let line = self.line(&detail(), false);
+            self.draw(&line);
+        }
+    }
+    /// Draw the final line, whatever the time since the last one.
+    pub fn finish(&mut self, detail: &str) {
+        if self.verbosity > Verbosity::Quiet {
+            let line = self.line(detail, true);
+            self.draw(&line);
+            if self.terminal && self.verbosity == Verbosity::Normal {
+                eprintln!();
+            }
+        }
+    }
+    fn line(&self, detail: &str, finished: bool) -> String {
+        let elapsed = self.start.elapsed().as_secs_f32();
+        let rate = self.done as f32 / elapsed.max(1e-3);
+        let mut line = format!(
+            "{}: {}/{} {}, {:.0} {}/s",
+            self.label, self.items, self.total_items, self.item, rate, self.work
+        );
+        if !finished && self.done < self.total_work && rate > 0.0 {
+            let left = (self.total_work - self.done) as f32 / rate;
+            line += &format!(", ETA {}", duration(left));
+        } else {
+            line += &format!(", took {}", duration(elapsed));
+        }
+        if !detail.is_empty() {
+            line += ", ";
+            line += detail;
+        }
+        line
+    }
+    fn draw(&mut self, line: &str) {
+        let mut stderr = io::stderr().lock();
+        // A progress line that cannot be written is no reason to stop the work
+        let _ = if self.terminal && self.verbosity == Verbosity::Normal {
+            write!(stderr, "\r\x1b[K{}", line)
+        } else {
+            writeln!(stderr, "{}", line)
+        };
+        let _ = stderr.flush();
+        self.last_draw = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_rounded_to_two_units() {
+        assert_eq!(duration(0.4), "0s");
+        assert_eq!(duration(59.0), "59s");
+        assert_eq!(duration(61.0), "1m01s");
+        assert_eq!(duration(3725.0), "1h02m");
+    }
+
+    #[test]
+    fn lines_show_the_time_left_until_finished() {
+        let mut progress = Progress::new(
+            Verbosity::Quiet,
+            "search",
+            (10, "solutions"),
+            (1000, "iterations"),
+        );
+        progress.advance(2, 200, || unreachable!("quiet progress draws nothing"));
+        let line = progress.line("best crane", false);
+        assert!(line.starts_with("search: 2/10 solutions, "));
+        assert!(line.contains(" iterations/s, ETA "));
+        assert!(line.ends_with(", best crane"));
+        let line = progress.line("", true);
+        assert!(line.contains(", took "));
+        assert!(!line.contains("ETA"));
+        assert!(Verbosity::Quiet < Verbosity::Normal && Verbosity::Normal < Verbosity::Verbose);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::error::{Error, Result};
+use crate::guess::{FeedBack, Guess};
+use crate::progress::Verbosity;
+use crate::solver::Solver;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// A game followed by a client: the guesses played and the candidates left.
+#[derive(Clone, Debug)]
+struct Session {
+    guess: Guess,
+    history: Vec<String>,
+}
+
+/// Everything the requests share: the solver is loaded once at startup.
+struct State {
+    solver: Solver,
+    sessions: Mutex<HashMap<u64, Session>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Deserialize)]
+struct Play {
+    word: String,
+    /// Pattern such as `bgybb` or `⬛🟩🟨⬛⬛`
+    feedback: String,
+}
+
+/// Status code and JSON body of a response.
+type Reply = (u16, Value);
+
+/// Serve `solver` over HTTP on `address`, such as `127.0.0.1:8080`, until the process is killed.
+///
+/// Every request runs on a thread of its own, so a long search does not hold
+/// the others back. Endpoints, all answering JSON:
+///
+/// - `POST /sessions` starts a game,
+/// - `GET /sessions/{id}` shows its guesses and number of candidates,
+/// - `DELETE /sessions/{id}` forgets it,
+/// - `POST /sessions/{id}/guesses` plays `{"word": "crane", "feedback": "bgybb"}`,
+/// - `GET /sessions/{id}/suggestions` searches for the best words, as many
+///   as `?top=N` asks for,
+/// - `GET /sessions/{id}/candidates` lists the words still possible.
+pub fn serve(address: &str, solver: Solver) -> Result<()> {
+    let server = Server::http(address).map_err(|e| {
+        Error::Io(io::Error::other(format!(
+            "cannot listen on {}: {}",
+            address, e
+        )))
+    })?;
+    if solver.options().verbosity > Verbosity::Quiet {
+        eprintln!("listening on http://{}", address);
+    }
+    let state = Arc::new(State {
+        solver,
+        sessions: Mutex::new(HashMap::new()),
+        next_id: AtomicU64::new(1),
+    });
+    for request in server.incoming_requests() {
+        let state = Arc::clone(&state);
+        thread::spawn(move || handle(&state, request));
+    }
+    Ok(())
+}
+
+fn handle(state: &State, mut request: Request) {
+    let mut body = String::new();
+    let (status, value) = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => route(state, request.method(), request.url(), &body),
+        Err(e) => Err(Error::Io(e)),
+    }
+    .unwrap_or_else(|e| {
+        (
+            e.http_status(),
+            json!({ "error": { "kind": e.kind(), "message": e.to_string() } }),
+        )
+    });
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("static header is valid");
+    let response = Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        eprintln!("failed to answer a request: {}", e);
+    }
+}
+
+fn not_found(what: &str) -> Reply {
+    (
+        404,
+        json!({ "error": { "kind": "not_found", "message": format!("{} not found", what) } }),
+    )
+}
+
+fn route(state: &State, method: &Method, url: &str, body: &str) -> Result<Reply> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match (method, segments.as_slice()) {
+        (Method::Post, ["sessions"]) => Ok(create(state)),
+        (method, ["sessions", id, rest @ ..]) => {
+            let id = match id.parse::<u64>() {
+                Ok(id) => id,
+                Err(_) => return Ok(not_found("session")),
+            };
+            if let (Method::Post, ["guesses"]) = (method, rest) {
+                return play(state, id, body);
+            }
+            let session = match state.sessions.lock().unwrap().get(&id) {
+                Some(session) => session.clone(),
+                None => return Ok(not_found("session")),
+            };
+            match (method, rest) {
+                (Method::Get, []) => Ok((200, describe(id, &session))),
+                (Method::Delete, []) => {
+                    state.sessions.lock().unwrap().remove(&id);
+                    Ok((200, json!({ "session": id, "deleted": true })))
+                }
+                (Method::Get, ["suggestions"]) => {
+                    let mut options = state.solver.options().clone();
+                    if let Some(top) = query_value(query, "top") {
+                        options.top = top.parse().map_err(|_| {
+                            Error::InvalidInput(format!(
+                                "expected a number of suggestions, got {:?}",
+                                top
+                            ))
+                        })?;
+                    }
+                    let result = state.solver.search_with(
+                        &session.guess,
+                        session.history.len() as u8,
+                        &options,
+                    )?;
+                    Ok((200, json!({ "session": id, "result": result })))
+                }
+                (Method::Get, ["candidates"]) => Ok((
+                    200,
+                    json!({
+                        "session": id,
+                        "candidates": session.guess.solutions(state.solver.dict()),
+                    }),
+                )),
+                _ => Ok(not_found("endpoint")),
+            }
+        }
+        _ => Ok(not_found("endpoint")),
+    }
+}
+
+/// Value of `key` in a query string such as `top=3&x=y`.
+fn query_value<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(k, value)| (k == key).then_some(value))
+}
+
+fn describe(id: u64, session: &Session) -> Value {
+    json!({
+        "session": id,
+        "guesses": session.history,
+        "candidates": session.guess.num_solutions(),
+    })
+}
+
+fn create(state: &State) -> Reply {
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+    let session = Session {
+        guess: state.solver.new_game(),
+        history: Vec::new(),
+    };
+    let value = describe(id, &session);
+    state.sessions.lock().unwrap().insert(id, session);
+    (201, value)
+}
+
+fn play(state: &State, id: u64, body: &str) -> Result<Reply> {
+    let play: Play = serde_json::from_str(body)
+        .map_err(|e| Error::Parse(format!("expected {{\"word\", \"feedback\"}}: {}", e)))?;
+    let feedback = FeedBack::parse(&play.feedback)?;
+    let word = state.solver.normalize(&play.word);
+    // Held until the play is recorded, so plays sent at once all land
+    let mut sessions = state.sessions.lock().unwrap();
+    let session = match sessions.get_mut(&id) {
+        Some(session) => session,
+        None => return Ok(not_found("session")),
+    };
+    let guess = state.solver.refine(&session.guess, &word, &feedback)?;
+    let entry = format!(
+        "{}:{}",
+        word,
+        feedback.iter().map(|fb| fb.to_char()).collect::<String>()
+    );
+    if guess.num_solutions() == 0 {
+        let history: Vec<&str> = session
+            .history
+            .iter()
+            .map(String::as_str)
+            .chain([entry.as_str()])
+            .collect();
+        return Err(Error::InvalidInput(format!(
+            "no word of the dictionary matches {}",
+            history.join(" ")
+        )));
+    }
+    session.history.push(entry);
+    session.guess = guess;
+    Ok((200, describe(id, session)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PatternTable;
+    use crate::mcts::SearchOptions;
+    use crate::store::tests::temp_store;
+
+    fn state() -> State {
+        let dict: Vec<String> = ["about", "agate", "actor", "angry", "ankle"]
+            .map(String::from)
+            .to_vec();
+        let weights = vec![1.0; dict.len()];
+        let options = SearchOptions {
+            iterations: 10,
+            threads: 1,
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        State {
+            solver: Solver::new(dict, weights, PatternTable::new(), temp_store(), options),
+            sessions: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    #[test]
+    fn a_session_follows_the_guesses_played() {
+        let state = state();
+        let (status, created) = route(&state, &Method::Post, "/sessions", "").unwrap();
+        assert_eq!((status, &created["candidates"]), (201, &json!(5)));
+        let (status, played) = route(
+            &state,
+            &Method::Post,
+            "/sessions/1/guesses",
+            r#"{"word": "ABOUT", "feedback": "gbbbb"}"#,
+        )
+        .unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(played["guesses"], json!(["about:gbbbb"]));
+        let (_, candidates) = route(&state, &Method::Get, "/sessions/1/candidates", "").unwrap();
+        assert_eq!(candidates["candidates"], json!(["angry", "ankle"]));
+        let (status, suggestions) =
+            route(&state, &Method::Get, "/sessions/1/suggestions?top=1", "").unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(
+            suggestions["result"]["suggestions"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(route(&state, &Method::Get, "/sessions/1/suggestions?top=x", "").is_err());
+        assert_eq!(
+            route(&state, &Method::Delete, "/sessions/1", "").unwrap().0,
+            200
+        );
+        assert_eq!(
+            route(&state, &Method::Get, "/sessions/1", "").unwrap().0,
+            404
+        );
+    }
+
+    #[test]
+    fn bad_requests_leave_the_session_unchanged() {
+        let state = state();
+        route(&state, &Method::Post, "/sessions", "").unwrap();
+        let play = |body: &str| route(&state, &Method::Post, "/sessions/1/guesses", body);
+        let status = |result: Result<Reply>| match result {
+            Ok((status, _)) => status,
+            Err(e) => e.http_status(),
+        };
+        assert_eq!(status(play("crane")), 400);
+        assert_eq!(
+            status(play(r#"{"word": "crane", "feedback": "bbbbb"}"#)),
+            400
+        );
+        assert_eq!(
+            status(play(r#"{"word": "about", "feedback": "bbbb"}"#)),
+            400
+        );
+        // No word of the dictionary gives this feedback
+        assert_eq!(
+            status(play(r#"{"word": "about", "feedback": "ggggy"}"#)),
+            400
+        );
+        let (_, session) = route(&state, &Method::Get, "/sessions/1", "").unwrap();
+        assert_eq!(session["candidates"], json!(5));
+        assert_eq!(
+            route(&state, &Method::Get, "/sessions/x", "").unwrap().0,
+            404
+        );
+        assert_eq!(route(&state, &Method::Put, "/sessions", "").unwrap().0, 404);
+    }
+
+    #[test]
+    fn sessions_played_at_once_stay_apart() {
+        let state = state();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let (_, created) = route(&state, &Method::Post, "/sessions", "").unwrap();
+                    let url = format!("/sessions/{}/guesses", created["session"]);
+                    let body = r#"{"word": "about", "feedback": "gbbbb"}"#;
+                    route(&state, &Method::Post, &url, body).unwrap();
+                    route(
+                        &state,
+                        &Method::Get,
+                        &url.replace("guesses", "suggestions"),
+                        "",
+                    )
+                    .unwrap();
+                });
+            }
+        });
+        let sessions = state.sessions.lock().unwrap();
+        assert_eq!(sessions.len(), 4);
+        assert!(sessions
+            .values()
+            .all(|session| session.history == ["about:gbbbb"]));
+    }
+
+    #[test]
+    fn guesses_played_at_once_on_a_session_are_all_kept() {
+        let state = state();
+        route(&state, &Method::Post, "/sessions", "").unwrap();
+        let words = ["about", "agate", "actor"];
+        thread::scope(|scope| {
+            for word in words {
+                let state = &state;
+                scope.spawn(move || {
+                    // Every play agrees with the same answer, in whatever order they land
+                    let feedback: String = state
+                        .solver
+                        .evaluate(word, "ankle")
+                        .iter()
+                        .map(|fb| fb.to_char())
+                        .collect();
+                    let body = json!({ "word": word, "feedback": feedback }).to_string();
+                    route(state, &Method::Post, "/sessions/1/guesses", &body).unwrap();
+                });
+            }
+        });
+        let (_, session) = route(&state, &Method::Get, "/sessions/1", "").unwrap();
+        assert_eq!(session["guesses"].as_array().unwrap().len(), words.len());
+        assert_eq!(session["candidates"], json!(1));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::error::{Error, Result};
+use crate::guess::FeedBack;
+use serde::Serialize;
+
+/// Emoji presentation selector some platforms add after ⬛ and ⬜.
+const VARIATION_SELECTOR: char = '\u{fe0f}';
+
+/// Colours used for the tiles of a share grid.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Theme {
+    /// White instead of black tiles for letters not in the word
+    pub light: bool,
+    /// Orange and blue instead of green and yellow tiles
+    pub high_contrast: bool,
+}
+
+impl Theme {
+    pub fn tile(self, feedback: FeedBack) -> char {
+        match (feedback, self.high_contrast) {
+            (FeedBack::Green, false) => '🟩',
+            (FeedBack::Green, true) => '🟧',
+            (FeedBack::Yellow, false) => '🟨',
+            (FeedBack::Yellow, true) => '🟦',
+            (FeedBack::Black, _) if self.light => '⬜',
+            (FeedBack::Black, _) => '⬛',
+        }
+    }
+}
+
+/// Feedback of an emoji tile, in any theme.
+pub fn tile(c: char) -> Option<FeedBack> {
+    match c {
+        '🟩' | '🟧' => Some(FeedBack::Green),
+        '🟨' | '🟦' => Some(FeedBack::Yellow),
+        '⬛' | '⬜' => Some(FeedBack::Black),
+        _ => None,
+    }
+}
+
+/// A game as shared by Wordle, such as `Wordle 1,234 4/6*` and its grid.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Share {
+    /// Name of the game, `Wordle` for the original
+    pub game: Option<String>,
+    /// Puzzle number, kept as written
+    pub puzzle: Option<String>,
+    /// Number of guesses, `None` for a lost game (`X/6`)
+    pub guesses: Option<u8>,
+    /// Number of guesses allowed, `None` without a header or a limit (`4/∞`)
+    pub max_guess: Option<u8>,
+    /// Whether the score ended with `*`
+    pub hard_mode: bool,
+    #[serde(serialize_with = "serialize_rows")]
+    pub rows: Vec<Vec<FeedBack>>,
+}
+
+impl Share {
+    /// Whether the last row is all green.
+    pub fn is_won(&self) -> bool {
+        self.rows
+            .last()
+            .is_some_and(|row| row.iter().all(|&fb| fb == FeedBack::Green))
+    }
+}
+
+fn serialize_rows<S: serde::Serializer>(
+    rows: &[Vec<FeedBack>],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        rows.iter()
+            .map(|row| row.iter().map(|fb| fb.to_char()).collect::<String>()),
+    )
+}
+
+/// Parse one row of emoji tiles, `None` if the line is anything else.
+fn parse_row(line: &str) -> Option<Vec<FeedBack>> {
+    let row: Option<Vec<FeedBack>> = line
+        .chars()
+        .filter(|&c| c != VARIATION_SELECTOR && !c.is_whitespace())
+        .map(tile)
+        .collect();
+    row.filter(|row| !row.is_empty())
+}
+
+/// Parse a header such as `Wordle 1,234 4/6*`, the score being its last word.
+fn parse_header(line: &str, share: &mut Share) -> bool {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    let score = match words.pop() {
+        Some(score) => score,
+        None => return false,
+    };
+    let (guesses, max_guess) = match score.trim_end_matches('*').split_once('/') {
+        Some(score) => score,
+        None => return false,
+    };
+    let max_guess = match max_guess {
+        "∞" => None,
+        max_guess => match max_guess.parse() {
+            Ok(max_guess) => Some(max_guess),
+            Err(_) => return false,
+        },
+    };
+    share.guesses = match guesses {
+        "X" | "x" => None,
+        guesses => match guesses.parse() {
+            Ok(guesses) => Some(guesses),
+            Err(_) => return false,
+        },
+    };
+    share.max_guess = max_guess;
+    share.hard_mode = score.ends_with('*');
+    share.puzzle = (words.len() > 1).then(|| words.pop().unwrap().to_string());
+    share.game = (!words.is_empty()).then(|| words.join(" "));
+    true
+}
+
+/// Parse share text: an optional header line followed by rows of tiles.
+/// Any other line, such as a link, is ignored.
+pub fn parse(text: &str) -> Result<Share> {
+    let mut share = Share::default();
+    let mut has_header = false;
+    for line in text.lines() {
+        if let Some(row) = parse_row(line) {
+            if let Some(first) = share.rows.first() {
+                if first.len() != row.len() {
+                    return Err(Error::Parse(format!(
+                        "row {:?} has {} tiles, expected {}",
+                        line.trim(),
+                        row.len(),
+                        first.len()
+                    )));
+                }
+            }
+            share.rows.push(row);
+        } else if !has_header && share.rows.is_empty() {
+            has_header = parse_header(line, &mut share);
+        }
+    }
+    if share.rows.is_empty() {
+        return Err(Error::Parse("no row of tiles found".to_string()));
+    }
+    if let Some(guesses) = share.guesses {
+        if guesses as usize != share.rows.len() {
+            return Err(Error::Parse(format!(
+                "the score says {} guesses but the grid has {} rows",
+                guesses,
+                share.rows.len()
+            )));
+        }
+    }
+    Ok(share)
+}
+
+/// Share text of a game: a `game puzzle score/max_guess` header and one row per guess.
+///
+/// The game counts as won when the last row is all green. Games without a
+/// guess limit, such as Absurdle, are scored out of `∞`.
+pub fn generate(
+    game: &str,
+    puzzle: Option<&str>,
+    rows: &[Vec<FeedBack>],
+    max_guess: Option<u8>,
+    hard_mode: bool,
+    theme: Theme,
+) -> String {
+    let won = rows
+        .last()
+        .is_some_and(|row| row.iter().all(|&fb| fb == FeedBack::Green));
+    let score = if won {
+        rows.len().to_string()
+    } else {
+        "X".to_string()
+    };
+    let mut text = game.to_string();
+    if let Some(puzzle) = puzzle {
+        text += " ";
+        text += puzzle;
+    }
+    match max_guess {
+        Some(max_guess) => text += &format!(" {}/{}", score, max_guess),
+        None => text += &format!(" {}/∞", score),
+    }
+    if hard_mode {
+        text += "*";
+    }
+    text += "\n";
+    for row in rows {
+        text += "\n";
+        text.extend(row.iter().map(|&fb| theme.tile(fb)));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_games_are_read_with_their_header() {
+        let share =
+            parse("Wordle 1,234 3/6*\n\n⬛🟨⬛⬛⬛\n⬜️🟩🟨⬛️⬛\n🟩🟩🟩🟩🟩\nhttps://example.com\n")
+                .unwrap();
+        assert_eq!(share.game.as_deref(), Some("Wordle"));
+        assert_eq!(share.puzzle.as_deref(), Some("1,234"));
+        assert_eq!((share.guesses, share.max_guess), (Some(3), Some(6)));
+        assert!(share.hard_mode && share.is_won());
+        assert_eq!(share.rows[1], FeedBack::parse("bgybb").unwrap());
+    }
+
+    #[test]
+    fn lost_and_endless_games_have_no_score() {
+        let lost = parse("Wordle 12 X/6\n🟦🟧⬛⬛⬛\n").unwrap();
+        assert_eq!((lost.guesses, lost.max_guess), (None, Some(6)));
+        assert_eq!(lost.rows[0], FeedBack::parse("ygbbb").unwrap());
+        let absurdle = parse("Absurdle 1/∞\n🟩🟩🟩🟩🟩\n").unwrap();
+        assert_eq!(absurdle.max_guess, None);
+        assert_eq!(absurdle.puzzle, None);
+        // A grid alone is enough
+        assert!(parse("🟩🟩🟩🟩🟩").unwrap().game.is_none());
+    }
+
+    #[test]
+    fn inconsistent_grids_are_rejected() {
+        assert!(parse("Wordle 1 2/6\n🟩🟩🟩🟩🟩\n").is_err());
+        assert!(parse("⬛⬛⬛⬛⬛\n🟩🟩🟩🟩\n").is_err());
+        assert!(parse("Wordle 1 1/6\n").is_err());
+    }
+
+    #[test]
+    fn generated_text_reads_back() {
+        let rows = vec![
+            FeedBack::parse("bybbb").unwrap(),
+            FeedBack::parse("ggggg").unwrap(),
+        ];
+        let theme = Theme {
+            light: true,
+            high_contrast: true,
+        };
+        let text = generate("Wordle", Some("1,234"), &rows, Some(6), true, theme);
+        assert_eq!(text, "Wordle 1,234 2/6*\n\n⬜🟦⬜⬜⬜\n🟧🟧🟧🟧🟧");
+        let share = parse(&text).unwrap();
+        assert_eq!(share.rows, rows);
+        assert_eq!(share.guesses, Some(2));
+        let lost = generate("Absurdle", None, &rows[..1], None, false, Theme::default());
+        assert_eq!(lost, "Absurdle X/∞\n\n⬛🟨⬛⬛⬛");
+    }
+}
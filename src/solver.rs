@@ -0,0 +1,246 @@
+use crate::book::Book;
+use crate::cache::PatternTable;
+use crate::error::{Error, Result};
+use crate::guess::{FeedBack, Guess};
+use crate::mcts::{self, SearchOptions, SearchResult};
+use crate::store::StateStore;
+use crate::word::{Alphabet, Language};
+use std::{collections::HashMap, sync::Mutex};
+
+/// A dictionary, its pattern table and a state store, ready to be searched.
+pub struct Solver {
+    dict: Vec<String>,
+    weights: Vec<f32>,
+    cache: PatternTable,
+    store: StateStore,
+    options: SearchOptions,
+    /// Opening book results, by candidates and number of guesses
+    book: HashMap<(Guess, u8), SearchResult>,
+    /// Held by a search from loading its trees to saving them, so concurrent
+    /// searches never overwrite each other's statistics
+    searching: Mutex<()>,
+    /// Letters of the dictionary, to normalize the words played like its own
+    alphabet: Alphabet,
+    fold_accents: bool,
+}
+
+impl Solver {
+    /// `weights` holds the prior weight of each word of `dict` being the answer.
+    pub fn new(
+        dict: Vec<String>,
+        weights: Vec<f32>,
+        cache: PatternTable,
+        store: StateStore,
+        options: SearchOptions,
+    ) -> Self {
+        Self {
+            dict,
+            weights,
+            cache,
+            store,
+            options,
+            book: HashMap::new(),
+            searching: Mutex::new(()),
+            alphabet: Alphabet::for_language(Language::English),
+            fold_accents: true,
+        }
+    }
+    /// Normalize the words played with `alphabet`, as the dictionary was.
+    pub fn set_alphabet(&mut self, alphabet: Alphabet, fold_accents: bool) {
+        self.alphabet = alphabet;
+        self.fold_accents = fold_accents;
+    }
+    /// `word` as typed by the user, normalized the way the dictionary is.
+    pub fn normalize(&self, word: &str) -> String {
+        self.alphabet.normalize_input(word, self.fold_accents)
+    }
+    /// Answer searches from `book` when it covers them.
+    pub fn use_book(&mut self, book: &Book) -> Result<()> {
+        self.book = book.positions(self)?.into_iter().collect();
+        Ok(())
+    }
+    pub fn dict(&self) -> &[String] {
+        &self.dict
+    }
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+    pub fn cache(&self) -> &PatternTable {
+        &self.cache
+    }
+    pub fn options(&self) -> &SearchOptions {
+        &self.options
+    }
+    /// The candidate set before any guess, every word of the dictionary.
+    pub fn new_game(&self) -> Guess {
+        Guess::new(&self.dict)
+    }
+    /// Feedback of playing `word` when the answer is `solution`.
+    pub fn evaluate(&self, word: &str, solution: &str) -> Vec<FeedBack> {
+        FeedBack::evaluate(word, solution, &self.cache)
+    }
+    /// Narrow `guess` down to the words consistent with `feedback` for `word`,
+    /// letter counts included.
+    ///
+    /// Fails when `word` is not in the dictionary or `feedback` does not match its length.
+    pub fn refine(&self, guess: &Guess, word: &str, feedback: &[FeedBack]) -> Result<Guess> {
+        let word = &self.normalize(word);
+        if !self.dict.iter().any(|w| w == word) {
+            return Err(Error::InvalidInput(format!(
+                "{:?} is not in the dictionary",
+                word
+            )));
+        }
+        if feedback.len() != word.chars().count() {
+            return Err(Error::InvalidInput(format!(
+                "{} feedback tiles given for {:?}",
+                feedback.len(),
+                word
+            )));
+        }
+        guess.refine(word, feedback, &self.dict)
+    }
+    /// Replay `history` of `word:pattern` entries such as `crane:bgybb` from the start of a game.
+    pub fn replay(&self, history: &[String]) -> Result<Guess> {
+        let mut guess = self.new_game();
+        for entry in history {
+            let (word, pattern) = entry.split_once(':').ok_or_else(|| {
+                Error::InvalidInput(format!("expected word:pattern, got {:?}", entry))
+            })?;
+            guess = self.refine(&guess, word, &FeedBack::parse(pattern)?)?;
+            if guess.num_solutions() == 0 {
+                return Err(Error::InvalidInput(format!(
+                    "no word of the dictionary matches {}",
+                    history.join(" ")
+                )));
+            }
+        }
+        Ok(guess)
+    }
+    /// Rank the words to play from `guess`, `num_guess` guesses into the game.
+    pub fn search(&self, guess: &Guess, num_guess: u8) -> Result<SearchResult> {
+        self.search_with(guess, num_guess, &self.options)
+    }
+    /// Like [`Solver::search`], with other options than those the solver was built with.
+    pub fn search_with(
+        &self,
+        guess: &Guess,
+        num_guess: u8,
+        options: &SearchOptions,
+    ) -> Result<SearchResult> {
+        if let Some(result) = self.book.get(&(guess.clone(), num_guess)) {
+            let mut result = result.clone();
+            result.suggestions.truncate(options.top.max(1));
+            result.elapsed_ms = 0;
+            result.from_book = true;
+            return Ok(result);
+        }
+        self.search_live(guess, num_guess, options)
+    }
+    /// Like [`Solver::search_with`], without consulting the opening book.
+    ///
+    /// Waits for the other searches of the solver to finish first.
+    pub fn search_live(
+        &self,
+        guess: &Guess,
+        num_guess: u8,
+        options: &SearchOptions,
+    ) -> Result<SearchResult> {
+        let _searching = self.searching.lock().unwrap();
+        mcts::search(
+            guess.clone(),
+            num_guess,
+            &self.dict,
+            &self.weights,
+            &self.cache,
+            &self.store,
+            options,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Verbosity;
+    use crate::store::tests::temp_store;
+    use std::thread;
+
+    fn solver(words: &[&str], options: SearchOptions) -> Solver {
+        let dict: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        let weights = vec![1.0; dict.len()];
+        Solver::new(dict, weights, PatternTable::new(), temp_store(), options)
+    }
+
+    #[test]
+    fn concurrent_searches_keep_each_others_statistics() {
+        let options = SearchOptions {
+            iterations: 10,
+            threads: 2,
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        let solver = solver(&["crane", "crate", "trace", "slate", "plate"], options);
+        let guess = solver.new_game();
+        thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| solver.search(&guess, 0).unwrap());
+            }
+        });
+        let result = solver.search(&guess, 0).unwrap();
+        assert_eq!(result.simulations, 4 * 10 * 5);
+    }
+
+    #[test]
+    fn words_played_are_normalized_like_the_dictionary() {
+        let mut solver = solver(&["etude", "crane"], SearchOptions::default());
+        let guess = solver.new_game();
+        let feedback = FeedBack::parse("bbbbb").unwrap();
+        assert!(solver.refine(&guess, "ÉTUDE", &feedback).is_ok());
+        assert_eq!(solver.normalize("Crâne"), "crane");
+        // Unless the dictionary keeps its accents
+        solver.set_alphabet(Alphabet::for_language(Language::French), false);
+        assert_eq!(solver.normalize("ÉTUDE"), "étude");
+        assert!(solver.refine(&guess, "ÉTUDE", &feedback).is_err());
+    }
+
+    #[test]
+    fn replay_applies_the_letters_ruled_out() {
+        let solver = solver(
+            &["about", "agate", "actor", "angry", "ankle"],
+            SearchOptions::default(),
+        );
+        let guess = solver.replay(&["about:gbbbb".to_string()]).unwrap();
+        assert_eq!(guess.solutions(solver.dict()), ["angry", "ankle"]);
+        assert!(solver.replay(&["about:gbbb".to_string()]).is_err());
+        // Typed in capitals, as the feedback of a shared grid would be
+        assert_eq!(solver.replay(&["ABOUT:gbbbb".to_string()]).unwrap(), guess);
+        assert!(solver.replay(&["abouts:gbbbbb".to_string()]).is_err());
+    }
+
+    #[test]
+    fn next_turn_starts_from_the_statistics_of_the_last() {
+        let options = SearchOptions {
+            iterations: 200,
+            threads: 2,
+            verbosity: Verbosity::Quiet,
+            ..SearchOptions::default()
+        };
+        let words = [
+            "crane", "crate", "trace", "react", "cater", "caret", "slate", "plate", "grace",
+            "brace", "trade", "grade", "stare", "share", "spare", "scare", "snare", "irate",
+        ];
+        let solver = solver(&words, options.clone());
+        solver.search(&solver.new_game(), 0).unwrap();
+        // The position a player reaches, through the same path as the CLI
+        let guess = solver.replay(&["slate:bbgbg".to_string()]).unwrap();
+        assert!(guess.num_solutions() > 1);
+        let fresh = SearchOptions {
+            iterations: 1,
+            ..options
+        };
+        let result = solver.search_with(&guess, 1, &fresh).unwrap();
+        // Far more than the single iteration per candidate of this search
+        assert!(result.simulations > 10 * guess.num_solutions() as u32);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::error::Result;
+use crate::guess::Guess;
+use crate::mcts::{Node, StateSpace};
+use redb::{backends::InMemoryBackend, Database, ReadOnlyTable, ReadableDatabase, TableDefinition};
+use std::{collections::HashSet, path::Path, sync::Arc};
+
+// (solution, parent guess, word) -> (node guess, cumulative score, simulations, wins, num guess)
+type NodeKey<'a> = (&'a str, &'a [u8], &'a str);
+type NodeValue<'a> = (&'a [u8], f32, u32, u32, u8);
+const NODES: TableDefinition<NodeKey, NodeValue> = TableDefinition::new("nodes");
+// (solution, root guess) -> (iterations done, iterations planned)
+type RunKey<'a> = (&'a str, &'a [u8]);
+const RUNS: TableDefinition<RunKey, (u32, u32)> = TableDefinition::new("runs");
+
+/// Iterations of the search of one solution from one position.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Run {
+    pub done: u32,
+    pub planned: u32,
+}
+
+impl Run {
+    pub fn new(planned: usize) -> Self {
+        Self {
+            done: 0,
+            planned: planned as u32,
+        }
+    }
+    pub fn remaining(&self) -> usize {
+        self.planned.saturating_sub(self.done) as usize
+    }
+}
+
+/// Single-file store holding the MCTS statistics of every solution.
+///
+/// Nodes are keyed by solution first so one solution's tree can be read
+/// without touching the others. Readers run concurrently, writers are
+/// serialized by the database.
+#[derive(Clone)]
+pub struct StateStore {
+    db: Arc<Database>,
+}
+
+impl StateStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::init(Database::create(path)?)
+    }
+
+    /// A store kept in memory, gone with the process.
+    ///
+    /// For processes that would otherwise fight over the lock of one file,
+    /// such as several engines started by a referee.
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Database::builder().create_with_backend(InMemoryBackend::new())?)
+    }
+
+    fn init(db: Database) -> Result<Self> {
+        // Make sure the table exists so readers never see a missing table
+        let txn = db.begin_write()?;
+        txn.open_table(NODES)?;
+        txn.open_table(RUNS)?;
+        txn.commit()?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// Load the nodes explored so far for `solution` that a search from
+    /// `root` can reach, following the tree down from `root`.
+    ///
+    /// A search of a later turn so picks up the subtree an earlier turn built,
+    /// reading one key range per node reached and nothing else.
+    pub fn load_solution(
+        &self,
+        solution: &str,
+        root: &Guess,
+        dict_len: usize,
+    ) -> Result<StateSpace> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(NODES)?;
+        let mut state_space = StateSpace::new();
+        let mut seen = HashSet::from([root.to_bytes()]);
+        let mut pending = vec![root.to_bytes()];
+        while let Some(parent) = pending.pop() {
+            for guess in read_children(&table, solution, &parent, dict_len, &mut state_space)? {
+                if seen.insert(guess.clone()) {
+                    pending.push(guess);
+                }
+            }
+        }
+        Ok(state_space)
+    }
+
+    /// Load the nodes of `solution` played from `parent`, and `parent` itself
+    /// when it is the root of a search.
+    pub fn load_children(
+        &self,
+        solution: &str,
+        parent: &Guess,
+        dict_len: usize,
+    ) -> Result<StateSpace> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(NODES)?;
+        let mut state_space = StateSpace::new();
+        read_children(
+            &table,
+            solution,
+            &parent.to_bytes(),
+            dict_len,
+            &mut state_space,
+        )?;
+        Ok(state_space)
+    }
+
+    /// How far the last search of `solution` from `root` went.
+    pub fn load_run(&self, solution: &str, root: &Guess) -> Result<Option<Run>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(RUNS)?;
+        let run = table
+            .get((solution, root.to_bytes().as_slice()))?
+            .map(|value| {
+                let (done, planned) = value.value();
+                Run { done, planned }
+            });
+        Ok(run)
+    }
+
+    /// Write back the nodes of `solution` that changed since they were loaded
+    /// or last saved, together with how far the search from `root` went.
+    ///
+    /// Both land in one transaction, so a resumed search never counts
+    /// iterations whose statistics were lost.
+    pub fn save_solution(
+        &self,
+        solution: &str,
+        state_space: &mut StateSpace,
+        root: &Guess,
+        run: Run,
+    ) -> Result<()> {
+        self.save_solutions(root, [(solution, state_space, run)])
+    }
+
+    /// Like [`StateStore::save_solution`] for several solutions searched from
+    /// `root`, in a single transaction.
+    pub fn save_solutions<'a>(
+        &self,
+        root: &Guess,
+        solutions: impl IntoIterator<Item = (&'a str, &'a mut StateSpace, Run)>,
+    ) -> Result<()> {
+        let root_bytes = root.to_bytes();
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table(NODES)?;
+            let mut runs = txn.open_table(RUNS)?;
+            for (solution, state_space, run) in solutions {
+                for ((guess, word), node) in state_space.iter_mut().filter(|(_, node)| node.dirty) {
+                    table.insert(
+                        (solution, guess.to_bytes().as_slice(), word.as_str()),
+                        (
+                            node.guess.to_bytes().as_slice(),
+                            node.cumulative_score,
+                            node.num_simulations,
+                            node.num_wins,
+                            node.num_guess,
+                        ),
+                    )?;
+                    node.dirty = false;
+                }
+                runs.insert((solution, root_bytes.as_slice()), (run.done, run.planned))?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+/// Read the nodes of `solution` played from `parent` into `state_space`,
+/// returning the guess each of them leads to.
+fn read_children(
+    table: &ReadOnlyTable<NodeKey, NodeValue>,
+    solution: &str,
+    parent: &[u8],
+    dict_len: usize,
+    state_space: &mut StateSpace,
+) -> Result<Vec<Vec<u8>>> {
+    let mut guesses = Vec::new();
+    let from = Guess::from_bytes(parent, dict_len);
+    for entry in table.range((solution, parent, "")..)? {
+        let (key, value) = entry?;
+        let (key_solution, guess, word) = key.value();
+        if key_solution != solution || guess != parent {
+            break;
+        }
+        let (node_guess, cumulative_score, num_simulations, num_wins, num_guess) = value.value();
+        guesses.push(node_guess.to_vec());
+        state_space.insert(
+            (from.clone(), word.to_string()),
+            Node {
+                guess: Guess::from_bytes(node_guess, dict_len),
+                cumulative_score,
+                num_simulations,
+                num_wins,
+                num_guess,
+                dirty: false,
+            },
+        );
+    }
+    Ok(guesses)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An empty store for one test.
+    pub(crate) fn temp_store() -> StateStore {
+        StateStore::in_memory().unwrap()
+    }
+
+    fn node(guess: &Guess, num_simulations: u32) -> Node {
+        Node {
+            guess: guess.clone(),
+            cumulative_score: num_simulations as f32,
+            num_simulations,
+            num_wins: num_simulations,
+            num_guess: 1,
+            dirty: true,
+        }
+    }
+
+    #[test]
+    fn load_follows_the_tree_down_from_the_root() {
+        let dict: Vec<String> = ["crane", "crate", "trace", "slate", "pious"]
+            .map(String::from)
+            .to_vec();
+        let root = Guess::new(&dict);
+        let refine = |guess: &Guess, word: &str, solution: &str| {
+            let feedback = crate::guess::FeedBack::compute(word, solution);
+            guess.refine(word, &feedback, &dict).unwrap()
+        };
+        let child = refine(&root, "crane", "slate");
+        let grandchild = refine(&child, "crate", "slate");
+        // Played from a subset of the root that no node of the tree leads to
+        let stray = refine(&root, "pious", "crane");
+        assert!(stray != child && stray != grandchild);
+        let mut state_space = StateSpace::from([
+            ((root.clone(), String::new()), node(&root, 3)),
+            ((root.clone(), "crane".to_string()), node(&child, 3)),
+            ((child.clone(), "crate".to_string()), node(&grandchild, 2)),
+            ((stray.clone(), "slate".to_string()), node(&stray, 1)),
+        ]);
+        let store = temp_store();
+        store
+            .save_solution("slate", &mut state_space, &root, Run::new(3))
+            .unwrap();
+        assert!(state_space.values().all(|node| !node.dirty));
+
+        let loaded = store.load_solution("slate", &root, dict.len()).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert!(!loaded.contains_key(&(stray.clone(), "slate".to_string())));
+        assert_eq!(
+            loaded[&(child.clone(), "crate".to_string())].num_simulations,
+            2
+        );
+        // A later turn starts from the subtree its position leads to
+        assert_eq!(
+            store
+                .load_solution("slate", &child, dict.len())
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(store
+            .load_solution("crane", &root, dict.len())
+            .unwrap()
+            .is_empty());
+
+        let children = store.load_children("slate", &root, dict.len()).unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(store.load_run("slate", &root).unwrap(), Some(Run::new(3)));
+    }
+}
@@ -0,0 +1,534 @@
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use wordle_bot::{progress::Verbosity, Error, FeedBack, Guess, Result, SearchResult, Solver};
+
+const KEYBOARD: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+/// Column where the suggestions and candidates start.
+const PANEL: u16 = 36;
+
+/// Puts the terminal back the way it was, even when the UI fails.
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        queue!(stdout, EnterAlternateScreen, Hide)?;
+        stdout.flush()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        // Nothing better to do if the terminal cannot be restored
+        let mut stdout = io::stdout();
+        let _ = queue!(stdout, Show, LeaveAlternateScreen);
+        let _ = stdout.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Row {
+    word: String,
+    feedback: Vec<FeedBack>,
+}
+
+/// State of the UI: the board, the row being entered and the latest search.
+struct App {
+    solver: Arc<Solver>,
+    length: usize,
+    max_guess: u8,
+    rows: Vec<Row>,
+    letters: Vec<char>,
+    tiles: Vec<FeedBack>,
+    cursor: usize,
+    guess: Guess,
+    result: Option<Result<SearchResult>>,
+    /// When the running search started
+    searching: Option<Instant>,
+    /// Number of the latest search, older results are dropped
+    generation: u64,
+    /// Set to stop the running search once a newer one replaces it
+    cancel: Arc<AtomicBool>,
+    sender: Sender<(u64, Result<SearchResult>)>,
+    message: String,
+}
+
+/// Colours of a tile: background then foreground.
+fn colours(feedback: FeedBack) -> (Color, Color) {
+    match feedback {
+        FeedBack::Green => (Color::DarkGreen, Color::White),
+        FeedBack::Yellow => (Color::DarkYellow, Color::Black),
+        FeedBack::Black => (Color::DarkGrey, Color::White),
+    }
+}
+
+/// Next colour of a tile, cycling black, yellow, green.
+fn cycle(feedback: FeedBack, forward: bool) -> FeedBack {
+    match (feedback, forward) {
+        (FeedBack::Black, true) | (FeedBack::Green, false) => FeedBack::Yellow,
+        (FeedBack::Yellow, true) | (FeedBack::Black, false) => FeedBack::Green,
+        (FeedBack::Green, true) | (FeedBack::Yellow, false) => FeedBack::Black,
+    }
+}
+
+impl App {
+    fn new(solver: Solver, sender: Sender<(u64, Result<SearchResult>)>) -> Self {
+        let length = solver.dict().first().map_or(0, |w| w.chars().count());
+        let max_guess = solver.options().max_guess;
+        let guess = solver.new_game();
+        Self {
+            solver: Arc::new(solver),
+            length,
+            max_guess,
+            rows: Vec::new(),
+            letters: Vec::new(),
+            tiles: vec![FeedBack::Black; length],
+            cursor: 0,
+            guess,
+            result: None,
+            searching: None,
+            generation: 0,
+            cancel: Arc::new(AtomicBool::new(false)),
+            sender,
+            message: String::new(),
+        }
+    }
+    fn is_solved(&self) -> bool {
+        self.rows
+            .last()
+            .is_some_and(|row| row.feedback.iter().all(|&fb| fb == FeedBack::Green))
+    }
+    /// Search the current candidates on a thread of its own.
+    fn search(&mut self) {
+        self.generation += 1;
+        self.cancel.store(true, Ordering::SeqCst);
+        self.cancel = Arc::new(AtomicBool::new(false));
+        self.result = None;
+        if self.is_solved() || self.rows.len() >= self.max_guess as usize {
+            self.searching = None;
+            return;
+        }
+        self.searching = Some(Instant::now());
+        let (solver, guess, generation) = (
+            Arc::clone(&self.solver),
+            self.guess.clone(),
+            self.generation,
+        );
+        let num_guess = self.rows.len() as u8;
+        let (sender, cancel) = (self.sender.clone(), Arc::clone(&self.cancel));
+        thread::spawn(move || {
+            let mut options = solver.options().clone();
+            options.verbosity = Verbosity::Quiet;
+            options.cancel = Some(cancel);
+            // The UI may be gone by then, the result is not needed anymore
+            let _ = sender.send((generation, solver.search_with(&guess, num_guess, &options)));
+        });
+    }
+    /// Recompute the candidates from the committed rows.
+    fn replay(&mut self) -> Result<()> {
+        let mut guess = self.solver.new_game();
+        for row in &self.rows {
+            guess = self.solver.refine(&guess, &row.word, &row.feedback)?;
+        }
+        self.guess = guess;
+        Ok(())
+    }
+    fn commit(&mut self) {
+        if self.is_solved() || self.rows.len() >= self.max_guess as usize {
+            self.message = "the game is over, backspace to undo".to_string();
+            return;
+        }
+        let word: String = self.letters.iter().collect();
+        let guess = match self.solver.refine(&self.guess, &word, &self.tiles) {
+            Ok(guess) if guess.num_solutions() == 0 => {
+                self.message = format!("no word matches {} with these colours", word);
+                return;
+            }
+            Ok(guess) => guess,
+            Err(e) => {
+                self.message = e.to_string();
+                return;
+            }
+        };
+        self.guess = guess;
+        self.rows.push(Row {
+            word,
+            feedback: self.tiles.clone(),
+        });
+        self.letters.clear();
+        self.tiles = vec![FeedBack::Black; self.length];
+        self.cursor = 0;
+        self.message = if self.is_solved() {
+            format!("solved in {}", self.rows.len())
+        } else {
+            String::new()
+        };
+        self.search();
+    }
+    /// Handle a key, returning whether to quit.
+    fn key(&mut self, key: KeyEvent) -> Result<bool> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(true),
+            KeyCode::Char('c') if control => return Ok(true),
+            KeyCode::Char(' ') | KeyCode::Up => {
+                self.tiles[self.cursor] = cycle(self.tiles[self.cursor], true)
+            }
+            KeyCode::Down => self.tiles[self.cursor] = cycle(self.tiles[self.cursor], false),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.length - 1),
+            KeyCode::Char(c) if c.is_alphabetic() && self.letters.len() < self.length => {
+                self.letters
+                    .extend(self.solver.normalize(&c.to_string()).chars());
+                // Colouring starts from the first tile once the word is complete
+                self.cursor = self.letters.len() % self.length;
+            }
+            KeyCode::Backspace if self.letters.is_empty() && !self.rows.is_empty() => {
+                self.rows.pop();
+                self.replay()?;
+                self.message = String::new();
+                self.search();
+            }
+            KeyCode::Backspace => {
+                self.letters.pop();
+                self.cursor = self.letters.len().min(self.length - 1);
+            }
+            KeyCode::Tab => {
+                if let Some(Ok(result)) = &self.result {
+                    self.letters = result.best().word.chars().collect();
+                    self.cursor = 0;
+                }
+            }
+            KeyCode::Enter => self.commit(),
+            _ => {}
+        }
+        Ok(false)
+    }
+    fn draw_tile(
+        out: &mut impl Write,
+        letter: char,
+        feedback: Option<FeedBack>,
+        cursor: bool,
+    ) -> io::Result<()> {
+        match feedback {
+            Some(feedback) => {
+                let (background, foreground) = colours(feedback);
+                queue!(
+                    out,
+                    SetBackgroundColor(background),
+                    SetForegroundColor(foreground)
+                )?;
+            }
+            None => queue!(out, SetAttribute(Attribute::Reverse))?,
+        }
+        if cursor {
+            queue!(out, SetAttribute(Attribute::Underlined))?;
+        }
+        queue!(
+            out,
+            Print(format!(" {} ", letter.to_uppercase())),
+            SetAttribute(Attribute::Reset),
+            ResetColor,
+            Print(" ")
+        )
+    }
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        queue!(
+            out,
+            Clear(ClearType::All),
+            MoveTo(2, 0),
+            SetAttribute(Attribute::Bold)
+        )?;
+        queue!(
+            out,
+            Print(format!(
+                "wordle-bot  {} candidates",
+                self.guess.num_solutions()
+            )),
+            SetAttribute(Attribute::Reset)
+        )?;
+        // Board
+        for i in 0..self.max_guess as usize {
+            queue!(out, MoveTo(2, 2 + i as u16 * 2))?;
+            if let Some(row) = self.rows.get(i) {
+                for (c, &fb) in row.word.chars().zip(&row.feedback) {
+                    Self::draw_tile(out, c, Some(fb), false)?;
+                }
+            } else if i == self.rows.len() && !self.is_solved() {
+                for j in 0..self.length {
+                    let letter = self.letters.get(j).copied().unwrap_or(' ');
+                    Self::draw_tile(out, letter, Some(self.tiles[j]), j == self.cursor)?;
+                }
+            } else {
+                for _ in 0..self.length {
+                    queue!(out, Print(" · "), Print(" "))?;
+                }
+            }
+        }
+        // Keyboard, each letter coloured by the best feedback it got
+        let top = 3 + self.max_guess as u16 * 2;
+        for (i, keys) in KEYBOARD.iter().enumerate() {
+            queue!(out, MoveTo(2 + i as u16 * 2, top + i as u16 * 2))?;
+            for key in keys.chars() {
+                let state = self
+                    .rows
+                    .iter()
+                    .flat_map(|row| row.word.chars().zip(row.feedback.iter().copied()))
+                    .filter(|&(c, _)| c == key)
+                    .map(|(_, fb)| fb)
+                    .max();
+                Self::draw_tile(out, key, state, false)?;
+            }
+        }
+        // Suggestions
+        let panel_width = width.saturating_sub(PANEL) as usize;
+        queue!(
+            out,
+            MoveTo(PANEL, 2),
+            SetAttribute(Attribute::Bold),
+            Print("Suggestions")
+        )?;
+        queue!(out, SetAttribute(Attribute::Reset))?;
+        let mut line = 3;
+        match (&self.result, self.searching) {
+            (Some(Ok(result)), _) => {
+                for (i, s) in result.suggestions.iter().enumerate() {
+                    let text = format!(
+                        "{}. {}  {:.2}  {:.0}%  ~{:.1} left{}",
+                        i + 1,
+                        s.word,
+                        s.mean_score,
+                        s.win_probability * 100.0,
+                        s.expected_remaining,
+                        if s.is_candidate {
+                            ""
+                        } else {
+                            "  (not a candidate)"
+                        }
+                    );
+                    queue!(
+                        out,
+                        MoveTo(PANEL, line),
+                        Print(truncate(&text, panel_width))
+                    )?;
+                    line += 1;
+                }
+            }
+            (Some(Err(e)), _) => {
+                queue!(
+                    out,
+                    MoveTo(PANEL, line),
+                    Print(truncate(&e.to_string(), panel_width))
+                )?;
+                line += 1;
+            }
+            (None, Some(start)) => {
+                let text = format!("thinking... {:.1}s", start.elapsed().as_secs_f32());
+                queue!(out, MoveTo(PANEL, line), Print(text))?;
+                line += 1;
+            }
+            (None, None) => {}
+        }
+        // Candidates, as many as fit
+        line += 1;
+        queue!(out, MoveTo(PANEL, line), SetAttribute(Attribute::Bold))?;
+        queue!(
+            out,
+            Print(format!("Candidates ({})", self.guess.num_solutions())),
+            SetAttribute(Attribute::Reset)
+        )?;
+        line += 1;
+        let mut column = 0;
+        for word in self.guess.solutions(self.solver.dict()) {
+            if line + 3 >= height {
+                break;
+            }
+            let cell = word.chars().count() + 1;
+            if column + cell > panel_width {
+                line += 1;
+                column = 0;
+                if line + 3 >= height {
+                    break;
+                }
+            }
+            queue!(out, MoveTo(PANEL + column as u16, line), Print(&word))?;
+            column += cell;
+        }
+        // Help and messages
+        let help = "type a word, space/arrows colour tiles, enter plays, tab takes the suggestion, backspace undoes, esc quits";
+        queue!(
+            out,
+            MoveTo(2, height.saturating_sub(2)),
+            Print(truncate(&self.message, (width as usize).saturating_sub(2))),
+            MoveTo(2, height.saturating_sub(1)),
+            SetForegroundColor(Color::DarkGrey),
+            Print(truncate(help, (width as usize).saturating_sub(2))),
+            ResetColor
+        )?;
+        out.flush()
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Run the full-screen UI until the user quits.
+///
+/// Feedback is entered by cycling the colour of each tile, and the
+/// suggestions are searched again in the background after every guess.
+pub fn run(solver: Solver) -> Result<()> {
+    if solver.dict().is_empty() {
+        return Err(Error::Dictionary("the dictionary is empty".to_string()));
+    }
+    let (sender, receiver): (_, Receiver<(u64, Result<SearchResult>)>) = mpsc::channel();
+    let mut app = App::new(solver, sender);
+    let _screen = Screen::enter()?;
+    let mut stdout = io::stdout();
+    app.search();
+    let mut redraw = true;
+    loop {
+        while let Ok((generation, result)) = receiver.try_recv() {
+            if generation == app.generation {
+                app.result = Some(result);
+                app.searching = None;
+                redraw = true;
+            }
+        }
+        // Keep the search timer ticking, otherwise only draw what changed
+        if redraw || app.searching.is_some() {
+            app.draw(&mut stdout)?;
+            redraw = false;
+        }
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                if app.key(key)? {
+                    return Ok(());
+                }
+                redraw = true;
+            }
+            Event::Resize(..) => redraw = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wordle_bot::{cache::PatternTable, store::StateStore, SearchOptions};
+
+    fn app() -> (App, Receiver<(u64, Result<SearchResult>)>) {
+        let dict: Vec<String> = ["about", "agate", "actor", "angry", "ankle"]
+            .map(String::from)
+            .to_vec();
+        let options = SearchOptions {
+            iterations: 10,
+            threads: 1,
+            ..SearchOptions::default()
+        };
+        let solver = Solver::new(
+            dict,
+            vec![1.0; 5],
+            PatternTable::new(),
+            StateStore::in_memory().unwrap(),
+            options,
+        );
+        let (sender, receiver) = mpsc::channel();
+        (App::new(solver, sender), receiver)
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> bool {
+        app.key(KeyEvent::new(code, KeyModifiers::NONE)).unwrap()
+    }
+
+    fn type_word(app: &mut App, word: &str) {
+        for c in word.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn tiles_cycle_through_the_three_colours() {
+        let mut feedback = FeedBack::Black;
+        for expected in [FeedBack::Yellow, FeedBack::Green, FeedBack::Black] {
+            feedback = cycle(feedback, true);
+            assert_eq!(feedback, expected);
+            assert_eq!(cycle(feedback, false), cycle(cycle(feedback, true), true));
+        }
+        assert_eq!(truncate("crane", 3), "cra");
+        assert_eq!(truncate("crane", 0), "");
+    }
+
+    #[test]
+    fn a_row_is_typed_coloured_and_committed() {
+        let (mut app, receiver) = app();
+        type_word(&mut app, "ABOUTS");
+        assert_eq!(app.letters.iter().collect::<String>(), "about");
+        // Colour the first tile green, the others stay black
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.rows.len(), 1);
+        assert_eq!(app.guess.solutions(app.solver.dict()), ["angry", "ankle"]);
+        let (generation, result) = receiver.recv().unwrap();
+        assert_eq!(generation, app.generation);
+        assert!(result.unwrap().suggestions.len() <= 2);
+        // Backspace on an empty row takes the last one back
+        press(&mut app, KeyCode::Backspace);
+        assert!(app.rows.is_empty());
+        assert_eq!(app.guess.num_solutions(), 5);
+        assert!(press(&mut app, KeyCode::Esc));
+    }
+
+    #[test]
+    fn a_row_no_word_matches_is_not_committed() {
+        let (mut app, _receiver) = app();
+        type_word(&mut app, "about");
+        for _ in 0..2 {
+            press(&mut app, KeyCode::Up);
+        }
+        press(&mut app, KeyCode::Right);
+        // A green b as second letter leaves no candidate
+        for _ in 0..2 {
+            press(&mut app, KeyCode::Up);
+        }
+        press(&mut app, KeyCode::Enter);
+        assert!(app.rows.is_empty());
+        assert!(app.message.starts_with("no word matches"));
+    }
+
+    #[test]
+    fn a_new_search_cancels_the_one_running() {
+        let (mut app, _receiver) = app();
+        app.search();
+        let first = Arc::clone(&app.cancel);
+        app.search();
+        assert!(first.load(Ordering::SeqCst));
+        assert!(!app.cancel.load(Ordering::SeqCst));
+        assert_eq!(app.generation, 2);
+    }
+}
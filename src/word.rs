@@ -1,13 +1,139 @@
-pub fn is_clean(word: &str) -> bool {
-    let mut chars = word.chars();
-    let first_char = chars.next().unwrap();
-    if first_char.is_uppercase() {
-        return false;
-    }
-    for c in chars {
-        if !c.is_ascii_alphabetic() {
-            return false;
+use crate::error::{Error, Result};
+use std::str::FromStr;
+
+/// Language of the dictionary, deciding which letters a word may use.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Language {
+    English,
+    Spanish,
+    German,
+    Norwegian,
+    French,
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "en" | "english" => Ok(Language::English),
+            "es" | "spanish" => Ok(Language::Spanish),
+            "de" | "german" => Ok(Language::German),
+            "no" | "norwegian" => Ok(Language::Norwegian),
+            "fr" | "french" => Ok(Language::French),
+            _ => Err(Error::InvalidInput(format!(
+                "unknown language {:?}, use en, es, de, no or fr",
+                s
+            ))),
+        }
+    }
+}
+
+const LATIN: &str = "abcdefghijklmnopqrstuvwxyz";
+
+/// Letters a language uses, split between letters of their own and accented
+/// forms that can be folded to their base letter.
+#[derive(Clone, Debug)]
+pub struct Alphabet {
+    letters: Vec<char>,
+    accents: Vec<char>,
+}
+
+impl Alphabet {
+    pub fn new(letters: &str, accents: &str) -> Self {
+        Self {
+            letters: letters.chars().collect(),
+            accents: accents.chars().collect(),
+        }
+    }
+    pub fn for_language(language: Language) -> Self {
+        match language {
+            Language::English => Self::new(LATIN, "àáâäçèéêëìíîïñòóôöùúûüýÿ"),
+            Language::Spanish => Self::new(&(LATIN.to_string() + "ñ"), "áéíóúü"),
+            Language::German => Self::new(&(LATIN.to_string() + "äöüß"), "àáâçèéêëñ"),
+            Language::Norwegian => Self::new(&(LATIN.to_string() + "æøå"), "àáâèéêòóôü"),
+            Language::French => Self::new(LATIN, "àâçèéêëîïôùûüÿ"),
         }
     }
-    true
+    /// Lowercase `word` and check it only uses letters of the alphabet.
+    ///
+    /// With `fold_accents`, accented letters become their base letter (é
+    /// becomes e), otherwise they are kept as letters of their own.
+    pub fn normalize(&self, word: &str, fold_accents: bool) -> Option<String> {
+        word.chars()
+            .flat_map(char::to_lowercase)
+            .map(|c| {
+                if self.letters.contains(&c) {
+                    Some(c)
+                } else if self.accents.contains(&c) {
+                    Some(if fold_accents { fold(c) } else { c })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+    /// Normalize a word typed by the user the way the dictionary words were.
+    ///
+    /// A word with letters outside the alphabet is only lowercased, so the
+    /// error that follows still names it.
+    pub fn normalize_input(&self, word: &str, fold_accents: bool) -> String {
+        self.normalize(word, fold_accents)
+            .unwrap_or_else(|| word.to_lowercase())
+    }
+}
+
+/// Base letter of an accented letter.
+fn fold(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        c => c,
+    }
+}
+
+/// Number of letters of `word`, which is not its length in bytes outside ASCII.
+pub fn len(word: &str) -> usize {
+    word.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_are_named_by_code_or_name() {
+        assert_eq!("es".parse::<Language>().unwrap(), Language::Spanish);
+        assert_eq!("german".parse::<Language>().unwrap(), Language::German);
+        assert!("klingon".parse::<Language>().is_err());
+    }
+
+    #[test]
+    fn accents_are_folded_or_kept_as_letters() {
+        let french = Alphabet::for_language(Language::French);
+        assert_eq!(french.normalize("Élève", true).unwrap(), "eleve");
+        assert_eq!(french.normalize("Élève", false).unwrap(), "élève");
+        // Letters of the language itself are never folded
+        let spanish = Alphabet::for_language(Language::Spanish);
+        assert_eq!(spanish.normalize("AÑEJO", true).unwrap(), "añejo");
+        let norwegian = Alphabet::for_language(Language::Norwegian);
+        assert_eq!(norwegian.normalize("brød", true).unwrap(), "brød");
+    }
+
+    #[test]
+    fn letters_outside_the_alphabet_are_rejected() {
+        let english = Alphabet::for_language(Language::English);
+        assert_eq!(english.normalize("brød", true), None);
+        assert_eq!(english.normalize("c4ane", true), None);
+        assert_eq!(english.normalize("it's", true), None);
+        // Typed input is still lowercased so the error names the word
+        assert_eq!(english.normalize_input("BRØD", true), "brød");
+        assert_eq!(len("brød"), 4);
+    }
 }